@@ -0,0 +1,70 @@
+use std::collections::HashSet;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use svc_authn::jose::{Algorithm, Config, ConfigMap, Key};
+use svc_authn::token::jws_compact::extract::decode_jws_compact_with_config;
+use svc_authn::token::jws_compact::{TokenBuilder, Verifier};
+use svc_authn::AccountId;
+
+fn config_map(algorithm: Algorithm, key: &[u8]) -> ConfigMap {
+    let audience = vec!["example.org".to_owned()]
+        .into_iter()
+        .collect::<HashSet<_>>();
+    let config = Config::new(audience, vec![Key::new(None, algorithm, key)]).unwrap();
+
+    let mut authn = ConfigMap::new();
+    authn.insert("iam.example.org".to_owned(), config);
+    authn
+}
+
+fn bench(c: &mut Criterion) {
+    let cases = [
+        (
+            "HS256",
+            Algorithm::HS256,
+            "data/keys/foo.public.der.example",
+            "data/keys/foo.public.der.example",
+        ),
+        (
+            "ES256",
+            Algorithm::ES256,
+            "data/keys/foo.private.der.example",
+            "data/keys/foo.public.der.example",
+        ),
+        (
+            "RS256",
+            Algorithm::RS256,
+            "data/keys/bar.private.pem.example",
+            "data/keys/bar.public.spki.pem.example",
+        ),
+    ];
+
+    let account = AccountId::new("john", "example.org");
+    for (name, algorithm, private_key, public_key) in cases.iter() {
+        let private_key = std::fs::read(private_key).unwrap();
+        let public_key = std::fs::read(public_key).unwrap();
+
+        let token = TokenBuilder::new()
+            .issuer("iam.example.org")
+            .subject(&account)
+            .key(*algorithm, &private_key)
+            .expires_in(3600)
+            .build()
+            .unwrap();
+
+        let authn = config_map(*algorithm, &public_key);
+        let verifier = Verifier::new(&authn).unwrap();
+
+        let mut group = c.benchmark_group(*name);
+        group.bench_function("config", |b| {
+            b.iter(|| decode_jws_compact_with_config::<String>(&token, &authn).unwrap())
+        });
+        group.bench_function("verifier", |b| {
+            b.iter(|| verifier.verify::<String>(&token).unwrap())
+        });
+        group.finish();
+    }
+}
+
+criterion_group!(benches, bench);
+criterion_main!(benches);
@@ -2,7 +2,7 @@ use std::fmt;
 use std::str::FromStr;
 
 use crate::Authenticable;
-use crate::Error;
+use crate::{Error, ErrorKind};
 
 ////////////////////////////////////////////////////////////////////////////////
 
@@ -31,6 +31,11 @@ impl AccountId {
     pub fn audience(&self) -> &str {
         &self.audience
     }
+
+    /// Parses the audience of the account which may be a cross-audience `{audience}:{target}`.
+    pub fn cross_audience(&self) -> Result<CrossAudience, Error> {
+        CrossAudience::from_str(&self.audience)
+    }
 }
 
 impl fmt::Display for AccountId {
@@ -62,14 +67,78 @@ impl Authenticable for AccountId {
 
 ////////////////////////////////////////////////////////////////////////////////
 
+/// An audience of an account with an optional target audience
+/// represented as `{audience}:{target}`.
+///
+/// A cross-audience token is issued for an account of the audience
+/// to be used in services of the target audience.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CrossAudience {
+    audience: String,
+    target: Option<String>,
+}
+
+impl CrossAudience {
+    pub fn new(audience: &str, target: Option<&str>) -> Self {
+        Self {
+            audience: audience.to_owned(),
+            target: target.map(ToOwned::to_owned),
+        }
+    }
+
+    pub fn audience(&self) -> &str {
+        &self.audience
+    }
+
+    pub fn target(&self) -> Option<&str> {
+        self.target.as_deref()
+    }
+}
+
+impl fmt::Display for CrossAudience {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self.target {
+            Some(ref target) => write!(fmt, "{}:{}", self.audience, target),
+            None => write!(fmt, "{}", self.audience),
+        }
+    }
+}
+
+impl FromStr for CrossAudience {
+    type Err = Error;
+
+    fn from_str(val: &str) -> Result<Self, Self::Err> {
+        match val.split_once(':') {
+            None if !val.is_empty() => Ok(Self::new(val, None)),
+            Some((audience, target)) if !audience.is_empty() && !target.is_empty() => {
+                Ok(Self::new(audience, Some(target)))
+            }
+            _ => Err(Error::with_kind(
+                ErrorKind::InvalidClaim,
+                &format!("invalid value for the audience: {}", val),
+            )),
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
 #[cfg(feature = "jose")]
 pub mod jose {
     use super::AccountId;
     use crate::jose::Claims;
 
+    /// The audience of the account is the verified audience of the claims as is,
+    /// so that the target of a cross-audience token is kept: see [`AccountId::cross_audience`].
+    impl From<&Claims<String>> for AccountId {
+        fn from(value: &Claims<String>) -> Self {
+            Self::new(value.subject(), value.audience())
+        }
+    }
+
     impl From<Claims<String>> for AccountId {
         fn from(value: Claims<String>) -> Self {
-            Self::new(value.subject(), value.audience())
+            Self::from(&value)
         }
     }
 }
@@ -110,7 +179,7 @@ mod serde {
     use serde::{de, ser};
     use std::fmt;
 
-    use super::AccountId;
+    use super::{AccountId, CrossAudience};
 
     impl ser::Serialize for AccountId {
         fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
@@ -149,4 +218,98 @@ mod serde {
             deserializer.deserialize_str(AccountIdVisitor)
         }
     }
+
+    impl ser::Serialize for CrossAudience {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: ser::Serializer,
+        {
+            serializer.serialize_str(&self.to_string())
+        }
+    }
+
+    impl<'de> de::Deserialize<'de> for CrossAudience {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: de::Deserializer<'de>,
+        {
+            struct CrossAudienceVisitor;
+
+            impl<'de> de::Visitor<'de> for CrossAudienceVisitor {
+                type Value = CrossAudience;
+
+                fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                    formatter.write_str("struct CrossAudience")
+                }
+
+                fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+                where
+                    E: de::Error,
+                {
+                    use std::str::FromStr;
+
+                    CrossAudience::from_str(v)
+                        .map_err(|_| de::Error::invalid_value(de::Unexpected::Str(v), &self))
+                }
+            }
+
+            deserializer.deserialize_str(CrossAudienceVisitor)
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_cross_audience() {
+        let value = CrossAudience::from_str("example.org:example.net").unwrap();
+        assert_eq!(value.audience(), "example.org");
+        assert_eq!(value.target(), Some("example.net"));
+        assert_eq!(value.to_string(), "example.org:example.net");
+
+        let value = CrossAudience::from_str("example.org").unwrap();
+        assert_eq!(value.target(), None);
+        assert_eq!(value.to_string(), "example.org");
+
+        for value in &["", ":example.net", "example.org:"] {
+            assert!(CrossAudience::from_str(value).is_err());
+        }
+
+        let account = AccountId::new("john", "example.org:example.net");
+        assert_eq!(
+            account.cross_audience().unwrap(),
+            CrossAudience::new("example.org", Some("example.net"))
+        );
+
+        let err = CrossAudience::from_str(":example.net").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidClaim);
+    }
+
+    #[cfg(feature = "jose")]
+    #[test]
+    fn derives_account_from_claims() {
+        use crate::jose::Claims;
+
+        let claims = Claims::new(
+            "iam.example.org",
+            "example.org:example.net",
+            "john".to_owned(),
+        );
+        let account = AccountId::from(&claims);
+        assert_eq!(account, AccountId::new("john", "example.org:example.net"));
+        assert_eq!(
+            account.cross_audience().unwrap(),
+            CrossAudience::new("example.org", Some("example.net"))
+        );
+        assert_eq!(AccountId::from(claims), account);
+
+        let claims = Claims::new("iam.example.org", "example.org", "john".to_owned());
+        let account = AccountId::from(&claims);
+        assert_eq!(account, AccountId::new("john", "example.org"));
+        assert_eq!(account.cross_audience().unwrap().target(), None);
+    }
 }
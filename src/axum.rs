@@ -0,0 +1,440 @@
+use std::error::Error as StdError;
+use std::fmt;
+use std::sync::Arc;
+
+use axum::extract::{FromRef, FromRequestParts, OptionalFromRequestParts};
+use axum::response::{IntoResponse, Response};
+use http1::header::{HeaderValue, AUTHORIZATION, WWW_AUTHENTICATE};
+use http1::request::Parts;
+use http1::StatusCode;
+
+use crate::jose::{Claims, ConfigMap, ReloadableConfigMap};
+use crate::token::bearer::challenge::Challenge;
+use crate::token::bearer::extract::parse_bearer_token;
+use crate::token::jws_compact::Verifier;
+use crate::{AccountId, Authenticable, Error, ErrorKind};
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// The state the extractors take from the application state via `FromRef`.
+///
+/// Tokens are verified by a [`Verifier`] prepared in advance, either a fixed one
+/// or the active one of a [`ReloadableConfigMap`].
+#[derive(Debug, Clone)]
+pub struct AuthnState {
+    verifier: VerifierSource,
+    realm: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+enum VerifierSource {
+    Fixed(Arc<Verifier>),
+    Reloadable(Arc<ReloadableConfigMap>),
+}
+
+impl AuthnState {
+    pub fn new(verifier: Arc<Verifier>) -> Self {
+        Self {
+            verifier: VerifierSource::Fixed(verifier),
+            realm: None,
+        }
+    }
+
+    /// Prepares a verifier for the config, see [`Verifier::new`].
+    pub fn from_config(authn: &ConfigMap) -> Result<Self, Error> {
+        Verifier::new(authn).map(|verifier| Self::new(Arc::new(verifier)))
+    }
+
+    /// Verifies tokens with whatever config the holder has loaded most recently.
+    pub fn reloadable(holder: Arc<ReloadableConfigMap>) -> Self {
+        Self {
+            verifier: VerifierSource::Reloadable(holder),
+            realm: None,
+        }
+    }
+
+    /// Sets the realm of the `WWW-Authenticate` challenges of rejections.
+    pub fn with_realm(self, value: &str) -> Self {
+        Self {
+            realm: Some(value.to_owned()),
+            ..self
+        }
+    }
+
+    pub fn verifier(&self) -> Arc<Verifier> {
+        match self.verifier {
+            VerifierSource::Fixed(ref verifier) => verifier.clone(),
+            VerifierSource::Reloadable(ref holder) => holder.verifier(),
+        }
+    }
+
+    pub fn realm(&self) -> Option<&str> {
+        self.realm.as_deref()
+    }
+
+    fn reject(&self, error: Error) -> Rejection {
+        Rejection {
+            error,
+            realm: self.realm.clone(),
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// An account authenticated by the token of the authorization header.
+///
+/// The token is verified with [`AuthnState`] taken from the application state.
+/// Use `Option<Authenticated>` for endpoints that allow anonymous access:
+/// requests without a bearer token in the authorization header are let through,
+/// invalid tokens are still rejected.
+#[derive(Debug)]
+pub struct Authenticated {
+    account: AccountId,
+    claims: Claims<String>,
+}
+
+impl Authenticated {
+    pub fn account(&self) -> &AccountId {
+        &self.account
+    }
+
+    pub fn claims(&self) -> &Claims<String> {
+        &self.claims
+    }
+
+    pub fn into_inner(self) -> (AccountId, Claims<String>) {
+        (self.account, self.claims)
+    }
+
+    fn verify(header: &HeaderValue, authn: &AuthnState) -> Result<Self, Rejection> {
+        parse_bearer_token(header)
+            .and_then(|token| authn.verifier().verify::<String>(token))
+            .map(|data| {
+                let claims = data.claims;
+                let account = AccountId::from(&claims);
+                Self { account, claims }
+            })
+            .map_err(|err| authn.reject(err))
+    }
+}
+
+impl Authenticable for Authenticated {
+    fn as_account_id(&self) -> &AccountId {
+        &self.account
+    }
+}
+
+impl<S> FromRequestParts<S> for Authenticated
+where
+    AuthnState: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = Rejection;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let authn = AuthnState::from_ref(state);
+        match parts.headers.get(AUTHORIZATION) {
+            Some(header) => Self::verify(header, &authn),
+            None => Err(authn.reject(Error::with_kind(
+                ErrorKind::MissingToken,
+                "missing authentication token",
+            ))),
+        }
+    }
+}
+
+impl<S> OptionalFromRequestParts<S> for Authenticated
+where
+    AuthnState: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = Rejection;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &S,
+    ) -> Result<Option<Self>, Self::Rejection> {
+        let authn = AuthnState::from_ref(state);
+        match parts.headers.get(AUTHORIZATION) {
+            Some(header) => match Self::verify(header, &authn) {
+                Err(rejection) if rejection.error.kind() == ErrorKind::UnsupportedScheme => {
+                    Ok(None)
+                }
+                result => result.map(Some),
+            },
+            None => Ok(None),
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// A rejection of the request that failed authentication.
+///
+/// It's responded with the `WWW-Authenticate` challenge built from the error.
+/// Failures of the server are responded with `500 Internal Server Error` and no challenge.
+#[derive(Debug)]
+pub struct Rejection {
+    error: Error,
+    realm: Option<String>,
+}
+
+impl Rejection {
+    pub fn error(&self) -> &Error {
+        &self.error
+    }
+
+    pub fn into_error(self) -> Error {
+        self.error
+    }
+}
+
+impl From<Error> for Rejection {
+    fn from(error: Error) -> Self {
+        Self { error, realm: None }
+    }
+}
+
+impl StdError for Rejection {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.error, fmt)
+    }
+}
+
+impl IntoResponse for Rejection {
+    fn into_response(self) -> Response {
+        if self.error.kind().is_server_error() {
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+
+        let mut challenge = Challenge::from_error(&self.error);
+        if let Some(ref realm) = self.realm {
+            challenge = challenge.realm(realm);
+        }
+
+        let header = challenge.to_http1_header_value();
+        (challenge.http1_status_code(), [(WWW_AUTHENTICATE, header)]).into_response()
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::sync::Arc;
+
+    use axum::extract::{FromRequestParts, OptionalFromRequestParts};
+    use axum::response::IntoResponse;
+    use http1::header::{AUTHORIZATION, WWW_AUTHENTICATE};
+    use http1::{Request, StatusCode};
+    use std::time::Duration;
+
+    use jsonwebtoken::Algorithm;
+
+    use super::*;
+    use crate::jose::{Config, ConfigMap, Key};
+    use crate::token::jws_compact::{InMemoryReplayCache, TokenBuilder};
+
+    fn config() -> ConfigMap {
+        let audience = vec!["example.org".to_owned()]
+            .into_iter()
+            .collect::<HashSet<_>>();
+        let config =
+            Config::new(audience, vec![Key::new(None, Algorithm::HS256, b"secret")]).unwrap();
+
+        let mut authn = ConfigMap::new();
+        authn.insert("iam.example.org".to_owned(), config);
+        authn
+    }
+
+    fn state() -> AuthnState {
+        AuthnState::from_config(&config()).unwrap()
+    }
+
+    fn parts(authorization: Option<&str>) -> Parts {
+        let mut builder = Request::builder().uri("/");
+        if let Some(value) = authorization {
+            builder = builder.header(AUTHORIZATION, value);
+        }
+
+        builder.body(()).unwrap().into_parts().0
+    }
+
+    fn token(key: &[u8]) -> String {
+        TokenBuilder::new()
+            .issuer("iam.example.org")
+            .subject(&AccountId::new("john", "example.org"))
+            .expires_in(60)
+            .key(Algorithm::HS256, key)
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn extracts_authenticated_account() {
+        let value = format!("Bearer {}", token(b"secret"));
+        let authenticated = <Authenticated as FromRequestParts<_>>::from_request_parts(
+            &mut parts(Some(&value)),
+            &state(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            authenticated.account(),
+            &AccountId::new("john", "example.org")
+        );
+        assert_eq!(authenticated.claims().issuer(), "iam.example.org");
+
+        let rejection =
+            <Authenticated as FromRequestParts<_>>::from_request_parts(&mut parts(None), &state())
+                .await
+                .unwrap_err();
+        let response = rejection.into_response();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(response.headers()[WWW_AUTHENTICATE], "Bearer");
+
+        let value = format!("Bearer {}", token(b"other"));
+        let rejection = <Authenticated as FromRequestParts<_>>::from_request_parts(
+            &mut parts(Some(&value)),
+            &state(),
+        )
+        .await
+        .unwrap_err();
+        let response = rejection.into_response();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert!(response.headers()[WWW_AUTHENTICATE]
+            .to_str()
+            .unwrap()
+            .starts_with("Bearer error=\"invalid_token\""));
+    }
+
+    #[tokio::test]
+    async fn derives_account_of_cross_audience_token() {
+        let token = TokenBuilder::new()
+            .issuer("iam.example.org")
+            .subject(&AccountId::new("john", "example.org"))
+            .target_audience("example.net")
+            .key(Algorithm::HS256, b"secret")
+            .build()
+            .unwrap();
+        let value = format!("Bearer {}", token);
+        let authenticated = <Authenticated as FromRequestParts<_>>::from_request_parts(
+            &mut parts(Some(&value)),
+            &state(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            authenticated.account(),
+            &AccountId::new("john", "example.org:example.net")
+        );
+        assert_eq!(
+            authenticated.account().cross_audience().unwrap().target(),
+            Some("example.net")
+        );
+        assert_eq!(
+            authenticated.account(),
+            &AccountId::from(authenticated.claims())
+        );
+    }
+
+    #[tokio::test]
+    async fn allows_anonymous_access() {
+        let anonymous = <Authenticated as OptionalFromRequestParts<_>>::from_request_parts(
+            &mut parts(None),
+            &state(),
+        )
+        .await
+        .unwrap();
+        assert!(anonymous.is_none());
+
+        let value = format!("Bearer {}", token(b"secret"));
+        let authenticated = <Authenticated as OptionalFromRequestParts<_>>::from_request_parts(
+            &mut parts(Some(&value)),
+            &state(),
+        )
+        .await
+        .unwrap();
+        assert!(authenticated.is_some());
+
+        let anonymous = <Authenticated as OptionalFromRequestParts<_>>::from_request_parts(
+            &mut parts(Some("Basic am9objpzZWNyZXQ=")),
+            &state(),
+        )
+        .await
+        .unwrap();
+        assert!(anonymous.is_none());
+
+        let rejection = <Authenticated as OptionalFromRequestParts<_>>::from_request_parts(
+            &mut parts(Some("Bearer")),
+            &state(),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(rejection.into_response().status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn challenges_with_realm() {
+        let state = state().with_realm("example.org");
+        let rejection =
+            <Authenticated as FromRequestParts<_>>::from_request_parts(&mut parts(None), &state)
+                .await
+                .unwrap_err();
+        let response = rejection.into_response();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            response.headers()[WWW_AUTHENTICATE],
+            "Bearer realm=\"example.org\""
+        );
+    }
+
+    #[tokio::test]
+    async fn verifies_with_reloadable_config() {
+        let holder = ReloadableConfigMap::new(|| Ok(config()))
+            .unwrap()
+            .with_replay_cache(Arc::new(InMemoryReplayCache::new(Duration::from_secs(60))));
+        let state = AuthnState::reloadable(Arc::new(holder));
+
+        let token = TokenBuilder::new()
+            .issuer("iam.example.org")
+            .subject(&AccountId::new("john", "example.org"))
+            .expires_in(60)
+            .token_id("1")
+            .key(Algorithm::HS256, b"secret")
+            .build()
+            .unwrap();
+        let value = format!("Bearer {}", token);
+        <Authenticated as FromRequestParts<_>>::from_request_parts(
+            &mut parts(Some(&value)),
+            &state,
+        )
+        .await
+        .unwrap();
+
+        let rejection = <Authenticated as FromRequestParts<_>>::from_request_parts(
+            &mut parts(Some(&value)),
+            &state,
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(rejection.error().kind(), ErrorKind::Replayed);
+        assert_eq!(rejection.into_response().status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[test]
+    fn hides_server_errors() {
+        let error = Error::with_kind(ErrorKind::Internal, "replay cache lock is poisoned");
+        let response = Rejection::from(error).into_response();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(response.headers().get(WWW_AUTHENTICATE).is_none());
+    }
+}
@@ -24,11 +24,14 @@ pub(crate) struct CliConfig {
 pub(crate) struct AudienceConfig {
     #[serde(deserialize_with = "svc_authn::serde::algorithm")]
     pub algorithm: svc_authn::jose::Algorithm,
-    #[serde(deserialize_with = "svc_authn::serde::file")]
+    #[serde(deserialize_with = "svc_authn::serde::key")]
     pub sign_key: Vec<u8>,
-    #[serde(deserialize_with = "svc_authn::serde::file")]
+    #[serde(deserialize_with = "svc_authn::serde::key")]
     pub verify_key: Vec<u8>,
     pub iss: String,
+    pub kid: Option<String>,
+    #[serde(default)]
+    pub leeway: u64,
 }
 
 impl CliConfig {
@@ -59,68 +59,82 @@ fn sign(
     cross_audience: &Option<String>,
     config: &CliConfig,
 ) -> Result<(), String> {
-    let (account_id, audience) = if let Some(ref cross_aud) = cross_audience {
-        let audience = AccountId::from_str(account_id)
-            .map_err(|err| format!("Failed to create account id: {}", err))?
-            .audience()
-            .to_owned();
-        let cross_account = format!("{}:{}", account_id, cross_aud);
-        let account_id = AccountId::from_str(&cross_account)
-            .map_err(|err| format!("Failed to create account id: {}", err))?;
-        (account_id, audience)
+    let token = build_token(account_id, expires_in, cross_audience, config)?;
+
+    if atty::is(Stream::Stdout) {
+        println!("{}", token);
     } else {
-        let account_id = AccountId::from_str(account_id)
-            .map_err(|err| format!("Failed to create account id: {}", err))?;
-        let audience = account_id.audience().to_owned();
-        (account_id, audience)
-    };
+        print!("{}", token);
+    }
+
+    Ok(())
+}
+
+fn build_token(
+    account_id: &str,
+    expires_in: i64,
+    cross_audience: &Option<String>,
+    config: &CliConfig,
+) -> Result<String, String> {
+    let account_id = AccountId::from_str(account_id)
+        .map_err(|err| format!("Failed to create account id: {}", err))?;
+    let audience = account_id.audience().to_owned();
 
     let audience_config = &config
         .audience
         .get(&audience)
         .ok_or_else(|| format!("Couldn't find audience: {} in config", audience))?;
 
-    let token = TokenBuilder::new()
+    let builder = TokenBuilder::new()
         .issuer(&audience_config.iss)
         .subject(&account_id)
         .key(
             audience_config.algorithm,
             audience_config.sign_key.as_slice(),
         )
-        .expires_in(expires_in)
-        .build()
-        .map_err(|err| format!("Error creating a token: {}", err))?;
+        .expires_in(expires_in);
 
-    if atty::is(Stream::Stdout) {
-        println!("{}", token);
-    } else {
-        print!("{}", token);
-    }
+    let builder = match cross_audience {
+        Some(ref target) => builder.target_audience(target),
+        None => builder,
+    };
 
-    Ok(())
+    let builder = match audience_config.kid {
+        Some(ref kid) => builder.key_id(kid),
+        None => builder,
+    };
+
+    builder
+        .build()
+        .map_err(|err| format!("Error creating a token: {}", err))
 }
 
 fn verify(token: &str, config: &CliConfig) -> Result<(), String> {
     use chrono::{DateTime, TimeZone, Utc};
     use jsonwebtoken::Validation;
+    use std::collections::HashSet;
 
     let nonvalidated_token: TokenData<Claims<String>> = extract::parse_jws_compact(token)
         .map_err(|err| format!("Error decoding token: {}", err))?;
     let claims = nonvalidated_token.claims;
-    let claims_audience = claims.audience().splitn(2, ':').collect::<Vec<&str>>()[0];
+    let cross_audience = claims
+        .cross_audience()
+        .map_err(|err| format!("Error decoding token: {}", err))?;
+    let claims_audience = cross_audience.audience();
     let has_exp_claim = claims.expiration_time().is_some();
+    let has_nbf_claim = claims.not_before().is_some();
 
     let audience_config = &config
         .audience
         .get(claims_audience)
         .ok_or_else(|| format!("Couldn't find audience: {} in config", claims_audience))?;
 
-    let verifier = Validation {
-        iss: Some(audience_config.iss.clone()),
-        algorithms: vec![audience_config.algorithm],
-        validate_exp: has_exp_claim,
-        ..Validation::default()
-    };
+    let mut verifier = Validation::new(audience_config.algorithm);
+    verifier.leeway = audience_config.leeway;
+    verifier.required_spec_claims = HashSet::new();
+    verifier.validate_exp = has_exp_claim;
+    verifier.validate_nbf = has_nbf_claim;
+    verifier.set_issuer(&[&audience_config.iss]);
 
     let valid_token: TokenData<Claims<String>> = extract::decode_jws_compact(
         token,
@@ -177,3 +191,46 @@ fn decode(token: &str) -> Result<(), String> {
 mod cli_config;
 mod extract_expiry;
 mod options;
+
+#[cfg(test)]
+mod tests {
+    use super::cli_config::AudienceConfig;
+    use super::*;
+
+    fn config() -> CliConfig {
+        let mut config = CliConfig::new(Some(60));
+        config.audience.insert(
+            "example.org".to_owned(),
+            AudienceConfig {
+                algorithm: svc_authn::jose::Algorithm::HS256,
+                sign_key: b"secret".to_vec(),
+                verify_key: b"secret".to_vec(),
+                iss: "iam.example.org".to_owned(),
+                kid: None,
+                leeway: 0,
+            },
+        );
+        config
+    }
+
+    #[test]
+    fn verifies_signed_token() {
+        let config = config();
+        let token = build_token("john.example.org", 60, &None, &config).unwrap();
+        verify(&token, &config).unwrap();
+
+        let token = build_token(
+            "john.example.org",
+            60,
+            &Some("example.net".to_owned()),
+            &config,
+        )
+        .unwrap();
+        verify(&token, &config).unwrap();
+
+        let token = build_token("john.example.org", 60, &None, &config).unwrap();
+        let mut forged = token.clone();
+        forged.push('x');
+        assert!(verify(&forged, &config).is_err());
+    }
+}
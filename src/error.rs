@@ -1,40 +1,173 @@
 use std::error::Error as StdError;
 use std::fmt::{self, Display};
 
+type Source = Box<dyn StdError + Send + Sync + 'static>;
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// A machine-readable kind of an error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// The authorization header can't be read.
+    MalformedHeader,
+    /// The authentication scheme isn't supported.
+    UnsupportedScheme,
+    /// The request carries the token improperly, e.g. using more than one method.
+    MalformedRequest,
+    /// The request carries no token.
+    MissingToken,
+    /// The token can't be parsed.
+    MalformedToken,
+    /// The issuer of the token isn't allowed.
+    UnknownIssuer,
+    /// The audience of the token isn't allowed.
+    DisallowedAudience,
+    /// The target audience of a cross-audience token isn't allowed.
+    DisallowedTarget,
+    /// There is no key for the key id of the token.
+    UnknownKey,
+    /// The algorithm of the token isn't supported or allowed.
+    UnsupportedAlgorithm,
+    /// The signature of the token doesn't match.
+    InvalidSignature,
+    /// The token has expired.
+    Expired,
+    /// The token isn't valid yet.
+    NotYetValid,
+    /// A claim required by the policy of the issuer is absent.
+    MissingClaim,
+    /// A claim has an invalid value.
+    InvalidClaim,
+    /// The lifetime of the token exceeds one allowed by the policy of the issuer.
+    LifetimeExceeded,
+    /// The token has already been used.
+    Replayed,
+    /// The token has been revoked.
+    Revoked,
+    /// A key can't be read or parsed.
+    InvalidKey,
+    /// A config can't be read or parsed.
+    InvalidConfig,
+    /// The verification can't be completed for a reason unrelated to the token,
+    /// e.g. a shared cache is unusable.
+    Internal,
+    /// Any other failure.
+    Other,
+}
+
+impl ErrorKind {
+    /// Whether the failure is caused by the server rather than the request,
+    /// e.g. an invalid key or config. Such errors are supposed to be responded with `5xx`
+    /// and their details shouldn't be disclosed to clients.
+    pub fn is_server_error(self) -> bool {
+        matches!(
+            self,
+            Self::InvalidKey | Self::InvalidConfig | Self::Internal | Self::Other
+        )
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 
 #[derive(Debug)]
-pub struct Error(String);
+pub struct Error {
+    kind: ErrorKind,
+    detail: String,
+    source: Option<Source>,
+}
 
 impl Error {
     pub fn new(detail: &str) -> Self {
-        Self(detail.to_owned())
+        Self::with_kind(ErrorKind::Other, detail)
+    }
+
+    pub fn with_kind(kind: ErrorKind, detail: &str) -> Self {
+        Self {
+            kind,
+            detail: detail.to_owned(),
+            source: None,
+        }
+    }
+
+    /// Attaches an underlying error, it doesn't change the message.
+    pub fn with_source<E>(self, source: E) -> Self
+    where
+        E: StdError + Send + Sync + 'static,
+    {
+        Self {
+            source: Some(Box::new(source)),
+            ..self
+        }
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
     }
 }
 
-impl StdError for Error {}
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        self.source
+            .as_deref()
+            .map(|err| err as &(dyn StdError + 'static))
+    }
+}
 
 impl Display for Error {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        fmt::Display::fmt(&self.0, fmt)
+        fmt::Display::fmt(&self.detail, fmt)
     }
 }
 
 ////////////////////////////////////////////////////////////////////////////////
 
 #[derive(Debug)]
-pub struct SerializationError(String);
+pub struct SerializationError {
+    kind: ErrorKind,
+    detail: String,
+    source: Option<Source>,
+}
 
 impl SerializationError {
     pub fn new(detail: &str) -> Self {
-        Self(detail.to_owned())
+        Self::with_kind(ErrorKind::Other, detail)
+    }
+
+    pub fn with_kind(kind: ErrorKind, detail: &str) -> Self {
+        Self {
+            kind,
+            detail: detail.to_owned(),
+            source: None,
+        }
+    }
+
+    /// Attaches an underlying error, it doesn't change the message.
+    pub fn with_source<E>(self, source: E) -> Self
+    where
+        E: StdError + Send + Sync + 'static,
+    {
+        Self {
+            source: Some(Box::new(source)),
+            ..self
+        }
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
     }
 }
 
-impl StdError for SerializationError {}
+impl StdError for SerializationError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        self.source
+            .as_deref()
+            .map(|err| err as &(dyn StdError + 'static))
+    }
+}
 
 impl Display for SerializationError {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        fmt::Display::fmt(&self.0, fmt)
+        fmt::Display::fmt(&self.detail, fmt)
     }
 }
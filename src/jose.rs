@@ -1,22 +1,373 @@
 use serde_derive::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::str::FromStr;
+
+use crate::CrossAudience;
+
+pub(crate) mod der;
+pub mod jwks;
+mod key_source;
+mod reload;
+
+pub use self::key_source::KeySource;
+pub use self::reload::ReloadableConfigMap;
 
 pub type ConfigMap = HashMap<String, Config>;
 
 #[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "ConfigRepr")]
 pub struct Config {
     audience: HashSet<String>,
-    #[serde(deserialize_with = "crate::serde::algorithm")]
-    algorithm: Algorithm,
-    #[serde(deserialize_with = "crate::serde::file")]
-    key: Vec<u8>,
+    keys: Vec<Key>,
+    leeway: u64,
+    policy: Policy,
+    allowed_targets: Option<HashSet<String>>,
 }
 
 impl Config {
+    pub fn new(audience: HashSet<String>, keys: Vec<Key>) -> Result<Self, crate::Error> {
+        if keys.is_empty() {
+            return Err(crate::Error::with_kind(
+                crate::ErrorKind::InvalidConfig,
+                "at least one key must be specified",
+            ));
+        }
+
+        for pattern in audience.iter().filter(|value| value.contains('*')) {
+            AudienceSet::suffix(pattern)?;
+        }
+
+        Ok(Self {
+            audience,
+            keys,
+            leeway: 0,
+            policy: Policy::default(),
+            allowed_targets: None,
+        })
+    }
+
+    /// Sets an allowed clock skew in seconds applied to time-based claims.
+    pub fn with_leeway(self, value: u64) -> Self {
+        Self {
+            leeway: value,
+            ..self
+        }
+    }
+
+    /// Creates a config with keys of a JSON Web Key Set (RFC 7517).
+    pub fn from_jwks(audience: HashSet<String>, jwks: &JwkSet) -> Result<Self, crate::Error> {
+        Self::new(audience, jwks::keys(jwks)?)
+    }
+
     pub fn audience(&self) -> &HashSet<String> {
         &self.audience
     }
 
+    pub fn keys(&self) -> &[Key] {
+        &self.keys
+    }
+
+    /// Sets claims requirements applied to tokens of the issuer.
+    pub fn with_policy(self, value: Policy) -> Self {
+        Self {
+            policy: value,
+            ..self
+        }
+    }
+
+    /// Restricts target audiences of cross-audience tokens of the issuer.
+    ///
+    /// Any target audience is allowed by default.
+    pub fn with_allowed_targets(self, value: HashSet<String>) -> Self {
+        Self {
+            allowed_targets: Some(value),
+            ..self
+        }
+    }
+
+    pub fn leeway(&self) -> u64 {
+        self.leeway
+    }
+
+    pub fn policy(&self) -> &Policy {
+        &self.policy
+    }
+
+    pub fn allowed_targets(&self) -> Option<&HashSet<String>> {
+        self.allowed_targets.as_ref()
+    }
+
+    /// An algorithm of the first key.
+    pub fn algorithm(&self) -> Algorithm {
+        self.keys[0].algorithm()
+    }
+
+    /// A value of the first key.
+    pub fn key(&self) -> &Vec<u8> {
+        self.keys[0].key()
+    }
+
+    /// Returns keys that may be used to verify a token with the `kid` header.
+    ///
+    /// When the token has no `kid`, all the keys are returned.
+    /// Otherwise, keys with the same identifier are returned or,
+    /// if there are none, keys without an identifier.
+    pub fn keys_for(&self, kid: Option<&str>) -> Vec<&Key> {
+        select_keys(&self.keys, kid, Key::kid)
+    }
+}
+
+/// Allowed audiences of an issuer.
+///
+/// An audience is either matched exactly or, if it's a pattern `*.{domain}`,
+/// any subdomain of the domain (of any depth but not the domain itself) is matched.
+/// Exact values are checked first.
+#[derive(Debug, Clone)]
+pub(crate) struct AudienceSet {
+    exact: HashSet<String>,
+    suffixes: Vec<String>,
+}
+
+impl AudienceSet {
+    pub(crate) fn new(values: &HashSet<String>) -> Result<Self, crate::Error> {
+        let mut exact = HashSet::new();
+        let mut suffixes = Vec::new();
+        for value in values {
+            if value.contains('*') {
+                suffixes.push(Self::suffix(value)?.to_owned());
+            } else {
+                exact.insert(value.to_owned());
+            }
+        }
+
+        Ok(Self { exact, suffixes })
+    }
+
+    pub(crate) fn contains(&self, audience: &str) -> bool {
+        self.exact.contains(audience)
+            || self
+                .suffixes
+                .iter()
+                .any(|suffix| audience.len() > suffix.len() && audience.ends_with(suffix.as_str()))
+    }
+
+    /// Returns a suffix of the pattern including the leading dot.
+    fn suffix(pattern: &str) -> Result<&str, crate::Error> {
+        match pattern.strip_prefix('*') {
+            Some(suffix)
+                if suffix.len() > 1 && suffix.starts_with('.') && !suffix.contains('*') =>
+            {
+                Ok(suffix)
+            }
+            _ => Err(crate::Error::with_kind(
+                crate::ErrorKind::InvalidConfig,
+                &format!(
+                    "invalid audience pattern = {}, only a leading '*.' is allowed",
+                    pattern
+                ),
+            )),
+        }
+    }
+}
+
+pub(crate) fn select_keys<'a, K, F>(keys: &'a [K], kid: Option<&str>, key_id: F) -> Vec<&'a K>
+where
+    F: Fn(&K) -> Option<&str>,
+{
+    let kid = match kid {
+        Some(kid) => kid,
+        None => return keys.iter().collect(),
+    };
+
+    let matched = keys
+        .iter()
+        .filter(|key| key_id(key) == Some(kid))
+        .collect::<Vec<&K>>();
+
+    if matched.is_empty() {
+        keys.iter().filter(|key| key_id(key).is_none()).collect()
+    } else {
+        matched
+    }
+}
+
+#[derive(Deserialize)]
+struct ConfigRepr {
+    audience: HashSet<String>,
+    #[serde(default, deserialize_with = "self::serde::optional_algorithm")]
+    algorithm: Option<Algorithm>,
+    #[serde(default, deserialize_with = "self::serde::optional_key")]
+    key: Option<Vec<u8>>,
+    #[serde(default)]
+    keys: Vec<Key>,
+    #[serde(default, deserialize_with = "self::serde::jwks")]
+    jwks: Vec<Key>,
+    #[serde(default)]
+    leeway: u64,
+    #[serde(default)]
+    policy: Policy,
+    #[serde(default)]
+    allowed_targets: Option<HashSet<String>>,
+}
+
+impl TryFrom<ConfigRepr> for Config {
+    type Error = crate::Error;
+
+    fn try_from(value: ConfigRepr) -> Result<Self, Self::Error> {
+        let mut keys = Vec::with_capacity(value.keys.len() + value.jwks.len() + 1);
+
+        match (value.algorithm, value.key) {
+            (Some(algorithm), Some(key)) => keys.push(Key {
+                kid: None,
+                algorithm,
+                key,
+            }),
+            (None, None) => (),
+            _ => {
+                return Err(crate::Error::with_kind(
+                    crate::ErrorKind::InvalidConfig,
+                    "algorithm and key must be specified together",
+                ))
+            }
+        }
+
+        keys.extend(value.keys);
+        keys.extend(value.jwks);
+        let mut config = Self::new(value.audience, keys)?
+            .with_leeway(value.leeway)
+            .with_policy(value.policy);
+        config.allowed_targets = value.allowed_targets;
+        Ok(config)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Requirements to claims of tokens of an issuer.
+///
+/// Nothing is required by default. Unknown fields are rejected,
+/// so that a misspelled requirement doesn't turn off silently.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Policy {
+    require_exp: bool,
+    max_lifetime: Option<u64>,
+    require_iat: bool,
+    require_nbf: bool,
+    require_jti: bool,
+}
+
+impl Policy {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn require_expiration_time(self, value: bool) -> Self {
+        Self {
+            require_exp: value,
+            ..self
+        }
+    }
+
+    /// Sets a maximum lifetime of tokens in seconds, i.e. a difference between `exp` and `iat`.
+    ///
+    /// Tokens without `exp` are rejected. For tokens without `iat`,
+    /// the remaining lifetime is checked instead.
+    pub fn max_lifetime(self, value: u64) -> Self {
+        Self {
+            max_lifetime: Some(value),
+            ..self
+        }
+    }
+
+    pub fn require_issued_at(self, value: bool) -> Self {
+        Self {
+            require_iat: value,
+            ..self
+        }
+    }
+
+    pub fn require_not_before(self, value: bool) -> Self {
+        Self {
+            require_nbf: value,
+            ..self
+        }
+    }
+
+    pub fn require_token_id(self, value: bool) -> Self {
+        Self {
+            require_jti: value,
+            ..self
+        }
+    }
+
+    /// Checks claims of a token whose signature has already been verified.
+    pub fn check<T>(&self, claims: &Claims<T>) -> Result<(), crate::Error> {
+        let missing = |name: &str| {
+            crate::Error::with_kind(
+                crate::ErrorKind::MissingClaim,
+                &format!("missing '{}' claim of the authentication token", name),
+            )
+        };
+
+        if (self.require_exp || self.max_lifetime.is_some()) && claims.exp.is_none() {
+            return Err(missing("exp"));
+        }
+        if self.require_iat && claims.iat.is_none() {
+            return Err(missing("iat"));
+        }
+        if self.require_nbf && claims.nbf.is_none() {
+            return Err(missing("nbf"));
+        }
+        if self.require_jti && claims.jti.is_none() {
+            return Err(missing("jti"));
+        }
+
+        if let (Some(max_lifetime), Some(exp)) = (self.max_lifetime, claims.exp) {
+            let iat = claims
+                .iat
+                .unwrap_or_else(|| chrono::Utc::now().timestamp() as u64);
+            if exp.saturating_sub(iat) > max_lifetime {
+                return Err(crate::Error::with_kind(
+                    crate::ErrorKind::LifetimeExceeded,
+                    &format!(
+                        "lifetime of the authentication token exceeds {} seconds",
+                        max_lifetime
+                    ),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Key {
+    #[serde(default)]
+    kid: Option<String>,
+    #[serde(deserialize_with = "crate::serde::algorithm")]
+    algorithm: Algorithm,
+    #[serde(deserialize_with = "crate::serde::key")]
+    key: Vec<u8>,
+}
+
+impl Key {
+    pub fn new(kid: Option<&str>, algorithm: Algorithm, key: &[u8]) -> Self {
+        Self {
+            kid: kid.map(ToOwned::to_owned),
+            algorithm,
+            key: key.to_owned(),
+        }
+    }
+
+    pub fn kid(&self) -> Option<&str> {
+        self.kid.as_deref()
+    }
+
     pub fn algorithm(&self) -> Algorithm {
         self.algorithm
     }
@@ -31,19 +382,38 @@ impl Config {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims<T> {
     iss: String,
-    aud: String,
+    #[serde(
+        serialize_with = "self::serde::serialize_audiences",
+        deserialize_with = "self::serde::audiences"
+    )]
+    aud: Vec<String>,
     sub: T,
     #[serde(skip_serializing_if = "Option::is_none")]
     exp: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    iat: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    nbf: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    jti: Option<String>,
+    #[serde(flatten)]
+    extra: serde_json::Map<String, serde_json::Value>,
+    #[serde(skip)]
+    verified_audience: Option<usize>,
 }
 
 impl<T> Claims<T> {
     pub fn new(iss: &str, aud: &str, sub: T) -> Self {
         Self {
             iss: iss.to_owned(),
-            aud: aud.to_owned(),
+            aud: vec![aud.to_owned()],
             sub,
             exp: None,
+            iat: None,
+            nbf: None,
+            jti: None,
+            extra: serde_json::Map::new(),
+            verified_audience: None,
         }
     }
 
@@ -52,14 +422,67 @@ impl<T> Claims<T> {
         self
     }
 
+    pub fn set_issued_at(&mut self, value: u64) -> &mut Self {
+        self.iat = Some(value);
+        self
+    }
+
+    pub fn set_not_before(&mut self, value: u64) -> &mut Self {
+        self.nbf = Some(value);
+        self
+    }
+
+    pub fn add_audience(&mut self, value: &str) -> &mut Self {
+        self.aud.push(value.to_owned());
+        self
+    }
+
+    pub fn set_token_id(&mut self, value: &str) -> &mut Self {
+        self.jti = Some(value.to_owned());
+        self
+    }
+
+    /// Sets a private claim, registered claim names aren't allowed.
+    pub fn set_extra_claim(
+        &mut self,
+        name: &str,
+        value: serde_json::Value,
+    ) -> Result<&mut Self, crate::Error> {
+        if REGISTERED_CLAIMS.contains(&name) {
+            return Err(crate::Error::with_kind(
+                crate::ErrorKind::InvalidClaim,
+                &format!("registered claim = {} can't be set as an extra one", name),
+            ));
+        }
+
+        self.extra.insert(name.to_owned(), value);
+        Ok(self)
+    }
+
     pub fn issuer(&self) -> &str {
         &self.iss
     }
 
+    /// The audience the token has been accepted for by the verifier
+    /// or, if the token hasn't been verified, the first one.
     pub fn audience(&self) -> &str {
+        &self.aud[self.verified_audience.unwrap_or(0)]
+    }
+
+    pub(crate) fn set_verified_audience(&mut self, index: usize) {
+        self.verified_audience = Some(index);
+    }
+
+    /// All audiences of the token, `aud` may be either a string or an array.
+    pub fn audiences(&self) -> &[String] {
         &self.aud
     }
 
+    /// Parses the audience of the token as a cross-audience, see [`Claims::audience`].
+    pub fn cross_audience(&self) -> Result<CrossAudience, crate::Error> {
+        CrossAudience::from_str(self.audience())
+    }
+
     pub fn subject(&self) -> &T {
         &self.sub
     }
@@ -67,14 +490,38 @@ impl<T> Claims<T> {
     pub fn expiration_time(&self) -> Option<u64> {
         self.exp
     }
+
+    pub fn issued_at(&self) -> Option<u64> {
+        self.iat
+    }
+
+    pub fn not_before(&self) -> Option<u64> {
+        self.nbf
+    }
+
+    pub fn token_id(&self) -> Option<&str> {
+        self.jti.as_deref()
+    }
+
+    pub fn extra_claim(&self, name: &str) -> Option<&serde_json::Value> {
+        self.extra.get(name)
+    }
+
+    /// Claims other than registered ones that are modeled explicitly.
+    pub fn extra_claims(&self) -> &serde_json::Map<String, serde_json::Value> {
+        &self.extra
+    }
 }
 
 ////////////////////////////////////////////////////////////////////////////////
 
 pub mod serde {
+    use super::{Key, KeySource};
     use jsonwebtoken::Algorithm;
-    use serde::de::{Deserializer, Error, Unexpected, Visitor};
+    use serde::de::{Deserializer, Error, SeqAccess, Unexpected, Visitor};
+    use serde::{Serialize, Serializer};
     use std::fmt;
+    use std::str::FromStr;
 
     ////////////////////////////////////////////////////////////////////////////////
 
@@ -94,9 +541,10 @@ pub mod serde {
         where
             E: Error,
         {
-            use std::str::FromStr;
-
-            Algorithm::from_str(v).map_err(|_| Error::invalid_value(Unexpected::Str(v), &self))
+            Algorithm::from_str(v)
+                .ok()
+                .filter(|algorithm| super::ALGORITHMS.contains(algorithm))
+                .ok_or_else(|| Error::invalid_value(Unexpected::Str(v), &self))
         }
     }
 
@@ -106,8 +554,267 @@ pub mod serde {
     {
         deserializer.deserialize_str(AlgorithmVisitor)
     }
+
+    pub(crate) fn optional_algorithm<'de, D>(deserializer: D) -> Result<Option<Algorithm>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        algorithm(deserializer).map(Some)
+    }
+
+    struct JwksVisitor;
+
+    impl<'de> Visitor<'de> for JwksVisitor {
+        type Value = Vec<Key>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            write!(
+                formatter,
+                "a source of JSON Web Key Set specified in RFC7517"
+            )
+        }
+
+        fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+        where
+            E: Error,
+        {
+            KeySource::from_str(v)
+                .and_then(|source| source.load())
+                .and_then(|data| super::jwks::from_slice(&data))
+                .map_err(Error::custom)
+        }
+    }
+
+    pub fn jwks<'de, D>(deserializer: D) -> Result<Vec<Key>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(JwksVisitor)
+    }
+
+    struct KeyVisitor;
+
+    impl<'de> Visitor<'de> for KeyVisitor {
+        type Value = Vec<u8>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            write!(
+                formatter,
+                "a path to an existing file or a key source prefixed with 'file:', 'env:', 'base64:' or 'inline:'"
+            )
+        }
+
+        fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+        where
+            E: Error,
+        {
+            // NOTE: the value isn't included into the error since it may contain the key itself.
+            KeySource::from_str(v)
+                .and_then(|source| source.load())
+                .map_err(Error::custom)
+        }
+    }
+
+    pub fn key<'de, D>(deserializer: D) -> Result<Vec<u8>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(KeyVisitor)
+    }
+
+    struct AudiencesVisitor;
+
+    impl<'de> Visitor<'de> for AudiencesVisitor {
+        type Value = Vec<String>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            write!(formatter, "a string or a non-empty array of strings")
+        }
+
+        fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+        where
+            E: Error,
+        {
+            Ok(vec![v.to_owned()])
+        }
+
+        fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+        where
+            A: SeqAccess<'de>,
+        {
+            let mut values = Vec::with_capacity(seq.size_hint().unwrap_or(1));
+            while let Some(value) = seq.next_element::<String>()? {
+                values.push(value);
+            }
+
+            if values.is_empty() {
+                return Err(Error::invalid_length(0, &self));
+            }
+
+            Ok(values)
+        }
+    }
+
+    pub(crate) fn audiences<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(AudiencesVisitor)
+    }
+
+    pub(crate) fn serialize_audiences<S>(value: &[String], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match value {
+            [audience] => serializer.serialize_str(audience),
+            values => values.serialize(serializer),
+        }
+    }
+
+    pub(crate) fn optional_key<'de, D>(deserializer: D) -> Result<Option<Vec<u8>>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        key(deserializer).map(Some)
+    }
 }
 
+const REGISTERED_CLAIMS: &[&str] = &["iss", "aud", "sub", "exp", "iat", "nbf", "jti"];
+
 ////////////////////////////////////////////////////////////////////////////////
 
+pub use jsonwebtoken::jwk::JwkSet;
 pub use jsonwebtoken::Algorithm;
+
+/// Algorithms supported for both signing and verification of tokens.
+pub const ALGORITHMS: &[Algorithm] = &[
+    Algorithm::HS256,
+    Algorithm::HS384,
+    Algorithm::HS512,
+    Algorithm::ES256,
+    Algorithm::ES384,
+    Algorithm::RS256,
+    Algorithm::RS384,
+    Algorithm::RS512,
+    Algorithm::PS256,
+    Algorithm::PS384,
+    Algorithm::PS512,
+    Algorithm::EdDSA,
+];
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deserializes_supported_algorithms() {
+        for name in &["HS384", "HS512", "ES384", "PS256", "EdDSA"] {
+            let config = serde_json::from_value::<Config>(serde_json::json!({
+                "audience": ["example.org"],
+                "algorithm": name,
+                "key": "data/keys/foo.public.der.example",
+            }))
+            .unwrap();
+
+            assert_eq!(format!("{:?}", config.algorithm()), *name);
+        }
+    }
+
+    #[test]
+    fn deserializes_audience_as_string_or_array() {
+        let claims = serde_json::from_value::<Claims<String>>(serde_json::json!({
+            "iss": "iam.example.org",
+            "aud": "example.org",
+            "sub": "john",
+        }))
+        .unwrap();
+        assert_eq!(claims.audiences(), ["example.org"]);
+        assert_eq!(serde_json::to_value(&claims).unwrap()["aud"], "example.org");
+
+        let claims = serde_json::from_value::<Claims<String>>(serde_json::json!({
+            "iss": "iam.example.org",
+            "aud": ["example.net", "example.org"],
+            "sub": "john",
+        }))
+        .unwrap();
+        assert_eq!(claims.audience(), "example.net");
+        assert_eq!(claims.audiences(), ["example.net", "example.org"]);
+
+        let result = serde_json::from_value::<Claims<String>>(serde_json::json!({
+            "iss": "iam.example.org",
+            "aud": [],
+            "sub": "john",
+        }));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn matches_audience_patterns() {
+        let values = vec!["example.org".to_owned(), "*.example.net".to_owned()]
+            .into_iter()
+            .collect();
+        let audience = AudienceSet::new(&values).unwrap();
+
+        assert!(audience.contains("example.org"));
+        assert!(!audience.contains("foo.example.org"));
+        assert!(audience.contains("foo.example.net"));
+        assert!(audience.contains("foo.bar.example.net"));
+        assert!(!audience.contains("example.net"));
+        assert!(!audience.contains(".example.net"));
+        assert!(!audience.contains("fooexample.net"));
+
+        for pattern in &[
+            "*",
+            "*.",
+            "foo.*.example.net",
+            "*example.net",
+            "*.*.example.net",
+        ] {
+            let values = vec![pattern.to_string()].into_iter().collect();
+            assert!(AudienceSet::new(&values).is_err());
+        }
+    }
+
+    #[test]
+    fn rejects_unsupported_algorithms() {
+        for name in &["none", "ES512", "RS1"] {
+            let result = serde_json::from_value::<Config>(serde_json::json!({
+                "audience": ["example.org"],
+                "algorithm": name,
+                "key": "data/keys/foo.public.der.example",
+            }));
+
+            assert!(result.is_err());
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_policy_fields() {
+        let config = serde_json::from_value::<Config>(serde_json::json!({
+            "audience": ["example.org"],
+            "algorithm": "HS256",
+            "key": "data/keys/foo.public.der.example",
+            "policy": { "max_lifetime": 60, "require_jti": true },
+        }))
+        .unwrap();
+        assert_eq!(config.policy().max_lifetime, Some(60));
+        assert!(config.policy().require_jti);
+
+        for policy in &[
+            serde_json::json!({ "max_lifetme": 60 }),
+            serde_json::json!({ "require_jit": true }),
+        ] {
+            let result = serde_json::from_value::<Config>(serde_json::json!({
+                "audience": ["example.org"],
+                "algorithm": "HS256",
+                "key": "data/keys/foo.public.der.example",
+                "policy": policy,
+            }));
+
+            assert!(result.is_err());
+        }
+    }
+}
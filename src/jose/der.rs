@@ -0,0 +1,291 @@
+use super::Algorithm;
+use crate::{Error, ErrorKind};
+
+////////////////////////////////////////////////////////////////////////////////
+
+const SEQUENCE: u8 = 0x30;
+const INTEGER: u8 = 0x02;
+const BIT_STRING: u8 = 0x03;
+const OCTET_STRING: u8 = 0x04;
+const OBJECT_IDENTIFIER: u8 = 0x06;
+
+// 1.2.840.10045.2.1
+const EC_PUBLIC_KEY: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01];
+// 1.2.840.10045.3.1.7
+const PRIME256V1: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07];
+// 1.3.132.0.34
+const SECP384R1: &[u8] = &[0x2b, 0x81, 0x04, 0x00, 0x22];
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Converts a PEM or DER encoded private key to the form expected by the signer:
+/// PKCS#8 for ECDSA and EdDSA, PKCS#1 for RSA and raw bytes for HMAC.
+pub(crate) fn private_key(key: &[u8], algorithm: Algorithm) -> Result<Vec<u8>, Error> {
+    let (tag, der) = match algorithm {
+        Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => return Ok(key.to_owned()),
+        _ => decode_pem(key)?,
+    };
+
+    let invalid = || {
+        Error::with_kind(
+            ErrorKind::InvalidKey,
+            &format!("invalid private key for algorithm {:?}", algorithm),
+        )
+    };
+    match algorithm {
+        Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => unreachable!(),
+        Algorithm::ES256 | Algorithm::ES384 => match tag.as_deref() {
+            Some("EC PRIVATE KEY") => Ok(sec1_to_pkcs8(&der, algorithm)),
+            Some("PRIVATE KEY") => Ok(der),
+            None if is_pkcs8(&der) => Ok(der),
+            None => Ok(sec1_to_pkcs8(&der, algorithm)),
+            _ => Err(invalid()),
+        },
+        Algorithm::RS256
+        | Algorithm::RS384
+        | Algorithm::RS512
+        | Algorithm::PS256
+        | Algorithm::PS384
+        | Algorithm::PS512 => match tag.as_deref() {
+            Some("RSA PRIVATE KEY") => Ok(der),
+            Some("PRIVATE KEY") => pkcs8_private_key(&der).ok_or_else(invalid),
+            None if is_pkcs8(&der) => pkcs8_private_key(&der).ok_or_else(invalid),
+            None => Ok(der),
+            _ => Err(invalid()),
+        },
+        Algorithm::EdDSA => match tag.as_deref() {
+            Some("PRIVATE KEY") | None => Ok(der),
+            _ => Err(invalid()),
+        },
+    }
+}
+
+/// Converts a PEM or DER encoded public key to the form expected by the verifier:
+/// an uncompressed point for ECDSA, PKCS#1 for RSA and raw bytes for EdDSA and HMAC.
+pub(crate) fn public_key(key: &[u8], algorithm: Algorithm) -> Result<Vec<u8>, Error> {
+    let (tag, der) = match algorithm {
+        Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => return Ok(key.to_owned()),
+        _ => decode_pem(key)?,
+    };
+
+    let invalid = || {
+        Error::with_kind(
+            ErrorKind::InvalidKey,
+            &format!("invalid public key for algorithm {:?}", algorithm),
+        )
+    };
+    match algorithm {
+        Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => unreachable!(),
+        Algorithm::RS256
+        | Algorithm::RS384
+        | Algorithm::RS512
+        | Algorithm::PS256
+        | Algorithm::PS384
+        | Algorithm::PS512 => match tag.as_deref() {
+            Some("RSA PUBLIC KEY") => Ok(der),
+            Some("PUBLIC KEY") => spki_public_key(&der).ok_or_else(invalid),
+            None if is_spki(&der) => spki_public_key(&der).ok_or_else(invalid),
+            None => Ok(der),
+            _ => Err(invalid()),
+        },
+        Algorithm::ES256 | Algorithm::ES384 | Algorithm::EdDSA => match tag.as_deref() {
+            Some("PUBLIC KEY") => spki_public_key(&der).ok_or_else(invalid),
+            None if is_spki(&der) => spki_public_key(&der).ok_or_else(invalid),
+            None => Ok(der),
+            _ => Err(invalid()),
+        },
+    }
+}
+
+fn decode_pem(key: &[u8]) -> Result<(Option<String>, Vec<u8>), Error> {
+    if !key.starts_with(b"-----BEGIN ") {
+        return Ok((None, key.to_owned()));
+    }
+
+    pem::parse(key)
+        .map(|pem| (Some(pem.tag), pem.contents))
+        .map_err(|err| {
+            Error::with_kind(
+                ErrorKind::InvalidKey,
+                &format!("invalid PEM encoded key – {}", err),
+            )
+            .with_source(err)
+        })
+}
+
+// PrivateKeyInfo ::= SEQUENCE { version INTEGER, algorithm AlgorithmIdentifier, privateKey OCTET STRING }
+fn is_pkcs8(der: &[u8]) -> bool {
+    let mut items = match parse(der) {
+        Some((SEQUENCE, content, _)) => Items(content),
+        _ => return false,
+    };
+
+    matches!(
+        (items.next(), items.next()),
+        (Some((INTEGER, _)), Some((SEQUENCE, _)))
+    )
+}
+
+fn pkcs8_private_key(der: &[u8]) -> Option<Vec<u8>> {
+    let (_, content, _) = parse(der).filter(|(tag, _, _)| *tag == SEQUENCE)?;
+    match Items(content).nth(2)? {
+        (OCTET_STRING, value) => Some(value.to_owned()),
+        _ => None,
+    }
+}
+
+// SubjectPublicKeyInfo ::= SEQUENCE { algorithm AlgorithmIdentifier, subjectPublicKey BIT STRING }
+fn is_spki(der: &[u8]) -> bool {
+    let mut items = match parse(der) {
+        Some((SEQUENCE, content, _)) => Items(content),
+        _ => return false,
+    };
+
+    matches!(
+        (items.next(), items.next()),
+        (Some((SEQUENCE, _)), Some((BIT_STRING, _)))
+    )
+}
+
+fn spki_public_key(der: &[u8]) -> Option<Vec<u8>> {
+    let (_, content, _) = parse(der).filter(|(tag, _, _)| *tag == SEQUENCE)?;
+    match Items(content).nth(1)? {
+        // The first byte is a number of unused bits which is always zero for keys.
+        (BIT_STRING, [0, value @ ..]) => Some(value.to_owned()),
+        _ => None,
+    }
+}
+
+fn sec1_to_pkcs8(der: &[u8], algorithm: Algorithm) -> Vec<u8> {
+    let curve = match algorithm {
+        Algorithm::ES384 => SECP384R1,
+        _ => PRIME256V1,
+    };
+
+    let mut identifier = encode(OBJECT_IDENTIFIER, EC_PUBLIC_KEY);
+    identifier.extend(encode(OBJECT_IDENTIFIER, curve));
+
+    let mut content = encode(INTEGER, &[0]);
+    content.extend(encode(SEQUENCE, &identifier));
+    content.extend(encode(OCTET_STRING, der));
+    encode(SEQUENCE, &content)
+}
+
+// RSAPublicKey ::= SEQUENCE { modulus INTEGER, publicExponent INTEGER }
+pub(crate) fn rsa_public_key(n: &[u8], e: &[u8]) -> Vec<u8> {
+    let mut content = integer(n);
+    content.extend(integer(e));
+    encode(SEQUENCE, &content)
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+fn integer(value: &[u8]) -> Vec<u8> {
+    let start = value.iter().position(|b| *b != 0).unwrap_or(value.len());
+    let value = &value[start..];
+
+    let mut content = Vec::with_capacity(value.len() + 1);
+    if !matches!(value.first(), Some(b) if b & 0x80 == 0) {
+        content.push(0);
+    }
+    content.extend_from_slice(value);
+    encode(INTEGER, &content)
+}
+
+fn encode(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut value = vec![tag];
+
+    let len = content.len();
+    if len < 0x80 {
+        value.push(len as u8);
+    } else {
+        let bytes = len.to_be_bytes();
+        let start = bytes
+            .iter()
+            .position(|b| *b != 0)
+            .unwrap_or(bytes.len() - 1);
+        value.push(0x80 | (bytes.len() - start) as u8);
+        value.extend_from_slice(&bytes[start..]);
+    }
+
+    value.extend_from_slice(content);
+    value
+}
+
+/// Splits the input into a tag, content and the rest.
+fn parse(der: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, rest) = der.split_first()?;
+    let (&len, rest) = rest.split_first()?;
+
+    let (len, rest) = if len < 0x80 {
+        (len as usize, rest)
+    } else {
+        let size = (len & 0x7f) as usize;
+        if size == 0 || size > std::mem::size_of::<usize>() || rest.len() < size {
+            return None;
+        }
+
+        let len = rest[..size]
+            .iter()
+            .fold(0usize, |acc, b| (acc << 8) | *b as usize);
+        (len, &rest[size..])
+    };
+
+    if rest.len() < len {
+        return None;
+    }
+
+    Some((tag, &rest[..len], &rest[len..]))
+}
+
+struct Items<'a>(&'a [u8]);
+
+impl<'a> Iterator for Items<'a> {
+    type Item = (u8, &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (tag, content, rest) = parse(self.0)?;
+        self.0 = rest;
+        Some((tag, content))
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_rsa_public_key() {
+        assert_eq!(
+            rsa_public_key(&[0x00, 0x81], &[0x01, 0x00, 0x01]),
+            vec![0x30, 0x09, 0x02, 0x02, 0x00, 0x81, 0x02, 0x03, 0x01, 0x00, 0x01]
+        );
+    }
+
+    #[test]
+    fn extracts_public_key_from_spki() {
+        let point = std::fs::read("data/keys/foo.public.der.example").unwrap();
+
+        let mut identifier = encode(OBJECT_IDENTIFIER, EC_PUBLIC_KEY);
+        identifier.extend(encode(OBJECT_IDENTIFIER, PRIME256V1));
+        let mut bit_string = vec![0];
+        bit_string.extend_from_slice(&point);
+        let mut content = encode(SEQUENCE, &identifier);
+        content.extend(encode(BIT_STRING, &bit_string));
+        let spki = encode(SEQUENCE, &content);
+
+        assert_eq!(public_key(&spki, Algorithm::ES256).unwrap(), point);
+        assert_eq!(public_key(&point, Algorithm::ES256).unwrap(), point);
+    }
+
+    #[test]
+    fn converts_sec1_to_pkcs8() {
+        let pkcs8 = std::fs::read("data/keys/foo.private.der.example").unwrap();
+        let sec1 = pkcs8_private_key(&pkcs8).unwrap();
+
+        assert_eq!(private_key(&pkcs8, Algorithm::ES256).unwrap(), pkcs8);
+        assert_eq!(private_key(&sec1, Algorithm::ES256).unwrap(), pkcs8);
+    }
+}
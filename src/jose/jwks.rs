@@ -0,0 +1,322 @@
+use std::path::Path;
+use std::str::FromStr;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use jsonwebtoken::jwk::{AlgorithmParameters, EllipticCurve, Jwk, JwkSet, PublicKeyUse};
+use serde_derive::Deserialize;
+
+use super::{der, Algorithm, Key};
+use crate::{Error, ErrorKind};
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Reads keys from a file with a JSON Web Key Set (RFC 7517).
+pub fn read<P>(path: P) -> Result<Vec<Key>, Error>
+where
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    let data = std::fs::read(path).map_err(|err| {
+        Error::with_kind(
+            ErrorKind::InvalidKey,
+            &format!(
+                "failed to read the key set from {} – {}",
+                path.display(),
+                err
+            ),
+        )
+        .with_source(err)
+    })?;
+
+    from_slice(&data)
+}
+
+/// Parses keys from JSON encoded JSON Web Key Set (RFC 7517).
+///
+/// See [`from_value`] for the keys being skipped.
+pub fn from_slice(data: &[u8]) -> Result<Vec<Key>, Error> {
+    let value = serde_json::from_slice::<serde_json::Value>(data).map_err(invalid_set)?;
+    from_value(value)
+}
+
+/// Converts a JSON value of a JSON Web Key Set (RFC 7517) to keys.
+///
+/// Keys intended for encryption as well as keys of types, curves or algorithms
+/// that aren't supported are skipped, the rest of the set is still used.
+pub fn from_value(value: serde_json::Value) -> Result<Vec<Key>, Error> {
+    #[derive(Deserialize)]
+    struct RawJwkSet {
+        keys: Vec<serde_json::Value>,
+    }
+
+    serde_json::from_value::<RawJwkSet>(value)
+        .map_err(invalid_set)?
+        .keys
+        .into_iter()
+        .filter(is_supported)
+        .map(|value| {
+            let jwk = serde_json::from_value::<Jwk>(value).map_err(|err| {
+                Error::with_kind(ErrorKind::InvalidKey, &format!("invalid key – {}", err))
+                    .with_source(err)
+            })?;
+
+            key(&jwk)
+        })
+        .collect()
+}
+
+/// Converts keys of the set skipping ones intended for encryption
+/// and ones of unsupported curves.
+pub fn keys(jwks: &JwkSet) -> Result<Vec<Key>, Error> {
+    jwks.keys
+        .iter()
+        .filter(|jwk| !matches!(jwk.common.public_key_use, Some(PublicKeyUse::Encryption)))
+        .filter(|jwk| has_supported_curve(jwk))
+        .map(key)
+        .collect()
+}
+
+/// Converts a single JSON Web Key to a key.
+///
+/// The algorithm is taken from the `alg` parameter or,
+/// if it's absent, derived from the key type and curve.
+pub fn key(jwk: &Jwk) -> Result<Key, Error> {
+    let (default_algorithm, value) = match jwk.algorithm {
+        AlgorithmParameters::EllipticCurve(ref params) => {
+            let algorithm = match params.curve {
+                EllipticCurve::P256 => Algorithm::ES256,
+                EllipticCurve::P384 => Algorithm::ES384,
+                ref curve => return Err(unsupported_curve(curve)),
+            };
+
+            let mut value = vec![0x04];
+            value.extend(decode(&params.x)?);
+            value.extend(decode(&params.y)?);
+            (algorithm, value)
+        }
+        AlgorithmParameters::RSA(ref params) => (
+            Algorithm::RS256,
+            der::rsa_public_key(&decode(&params.n)?, &decode(&params.e)?),
+        ),
+        AlgorithmParameters::OctetKey(ref params) => (Algorithm::HS256, decode(&params.value)?),
+        AlgorithmParameters::OctetKeyPair(ref params) => match params.curve {
+            EllipticCurve::Ed25519 => (Algorithm::EdDSA, decode(&params.x)?),
+            ref curve => return Err(unsupported_curve(curve)),
+        },
+    };
+
+    let algorithm = jwk.common.algorithm.unwrap_or(default_algorithm);
+    if family(algorithm) != family(default_algorithm) {
+        return Err(Error::with_kind(
+            ErrorKind::InvalidKey,
+            &format!(
+                "algorithm = {:?} doesn't match the type of the key",
+                algorithm
+            ),
+        ));
+    }
+
+    Ok(Key::new(jwk.common.key_id.as_deref(), algorithm, &value))
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+fn invalid_set(err: serde_json::Error) -> Error {
+    Error::with_kind(ErrorKind::InvalidKey, &format!("invalid key set – {}", err)).with_source(err)
+}
+
+/// Checks parameters of a raw key the set may contain for purposes other than ours.
+///
+/// Keys lacking the parameters aren't skipped, so that they're reported as invalid.
+fn is_supported(jwk: &serde_json::Value) -> bool {
+    let param = |name| jwk.get(name).and_then(serde_json::Value::as_str);
+
+    if param("use") == Some("enc") {
+        return false;
+    }
+
+    if let Some(alg) = param("alg") {
+        if Algorithm::from_str(alg).is_err() {
+            return false;
+        }
+    }
+
+    let curves: &[&str] = match param("kty") {
+        Some("EC") => &["P-256", "P-384"],
+        Some("OKP") => &["Ed25519"],
+        Some("RSA") | Some("oct") | None => return true,
+        Some(_) => return false,
+    };
+
+    match param("crv") {
+        Some(crv) => curves.contains(&crv),
+        None => true,
+    }
+}
+
+fn has_supported_curve(jwk: &Jwk) -> bool {
+    match jwk.algorithm {
+        AlgorithmParameters::EllipticCurve(ref params) => {
+            matches!(params.curve, EllipticCurve::P256 | EllipticCurve::P384)
+        }
+        AlgorithmParameters::OctetKeyPair(ref params) => params.curve == EllipticCurve::Ed25519,
+        _ => true,
+    }
+}
+
+fn decode(value: &str) -> Result<Vec<u8>, Error> {
+    URL_SAFE_NO_PAD.decode(value).map_err(|err| {
+        Error::with_kind(
+            ErrorKind::InvalidKey,
+            &format!("invalid key parameter – {}", err),
+        )
+        .with_source(err)
+    })
+}
+
+fn unsupported_curve(curve: &EllipticCurve) -> Error {
+    Error::with_kind(
+        ErrorKind::InvalidKey,
+        &format!("unsupported curve {:?}", curve),
+    )
+}
+
+fn family(algorithm: Algorithm) -> u8 {
+    match algorithm {
+        Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => 0,
+        Algorithm::ES256 | Algorithm::ES384 => 1,
+        Algorithm::RS256
+        | Algorithm::RS384
+        | Algorithm::RS512
+        | Algorithm::PS256
+        | Algorithm::PS384
+        | Algorithm::PS512 => 2,
+        Algorithm::EdDSA => 3,
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_keys() {
+        let keys = from_value(serde_json::json!({
+            "keys": [
+                {
+                    "kty": "EC",
+                    "kid": "foo",
+                    "crv": "P-256",
+                    "x": "2a6We1soiXiB0F4NmLW5Yt_PaECY26RO0lsojO66VWs",
+                    "y": "AMmFtxcK-jFgXsZ2O7SuxGgvB2QbxoCp1S7P0BxqRMA",
+                },
+                {
+                    "kty": "oct",
+                    "kid": "bar",
+                    "alg": "HS512",
+                    "k": "c2VjcmV0",
+                },
+                {
+                    "kty": "oct",
+                    "use": "enc",
+                    "k": "c2VjcmV0",
+                },
+            ]
+        }))
+        .unwrap();
+
+        assert_eq!(keys.len(), 2);
+        assert_eq!(keys[0].kid(), Some("foo"));
+        assert_eq!(keys[0].algorithm(), Algorithm::ES256);
+        assert_eq!(
+            keys[0].key(),
+            &std::fs::read("data/keys/foo.public.der.example").unwrap()
+        );
+        assert_eq!(keys[1].kid(), Some("bar"));
+        assert_eq!(keys[1].algorithm(), Algorithm::HS512);
+        assert_eq!(keys[1].key(), b"secret");
+    }
+
+    #[test]
+    fn skips_unsupported_keys() {
+        let value = serde_json::json!({
+            "keys": [
+                {
+                    "kty": "RSA",
+                    "kid": "enc",
+                    "use": "enc",
+                    "alg": "RSA-OAEP",
+                    "n": "AQAB",
+                    "e": "AQAB",
+                },
+                {
+                    "kty": "RSA",
+                    "kid": "unknown",
+                    "alg": "RSA1_5",
+                    "n": "AQAB",
+                    "e": "AQAB",
+                },
+                {
+                    "kty": "EC",
+                    "kid": "p521",
+                    "crv": "P-521",
+                    "x": "AQAB",
+                    "y": "AQAB",
+                },
+                {
+                    "kty": "OKP",
+                    "kid": "x25519",
+                    "crv": "X25519",
+                    "x": "AQAB",
+                },
+                {
+                    "kty": "oct",
+                    "kid": "bar",
+                    "k": "c2VjcmV0",
+                },
+            ]
+        });
+
+        let parsed = from_value(value.clone()).unwrap();
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0].kid(), Some("bar"));
+        assert_eq!(parsed[0].key(), b"secret");
+
+        let parsed = from_slice(value.to_string().as_bytes()).unwrap();
+        assert_eq!(parsed.len(), 1);
+
+        let jwks = serde_json::from_value::<JwkSet>(serde_json::json!({
+            "keys": [
+                { "kty": "EC", "crv": "P-521", "x": "AQAB", "y": "AQAB" },
+                { "kty": "oct", "kid": "bar", "k": "c2VjcmV0" },
+            ]
+        }))
+        .unwrap();
+        let converted = keys(&jwks).unwrap();
+        assert_eq!(converted.len(), 1);
+        assert_eq!(converted[0].kid(), Some("bar"));
+    }
+
+    #[test]
+    fn rejects_invalid_keys() {
+        let result = from_value(serde_json::json!({
+            "keys": [{ "kty": "oct", "kid": "bar" }]
+        }));
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::InvalidKey);
+
+        let result = from_value(serde_json::json!({ "jwks": [] }));
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::InvalidKey);
+    }
+
+    #[test]
+    fn rejects_mismatched_algorithm() {
+        let result = from_value(serde_json::json!({
+            "keys": [{ "kty": "oct", "alg": "ES256", "k": "c2VjcmV0" }]
+        }));
+
+        assert!(result.is_err());
+    }
+}
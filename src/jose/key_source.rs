@@ -0,0 +1,139 @@
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+
+use crate::{Error, ErrorKind};
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// A location of a key.
+///
+/// It's parsed from strings of the form:
+/// - `file:/path/to/key` or just `/path/to/key` – a file;
+/// - `env:NAME` – a value of the environment variable;
+/// - `base64:DATA` – base64 encoded data;
+/// - `inline:DATA` or a PEM encoded key itself – data as is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeySource {
+    File(PathBuf),
+    Env(String),
+    Base64(String),
+    Inline(String),
+}
+
+impl KeySource {
+    pub fn load(&self) -> Result<Vec<u8>, Error> {
+        match self {
+            Self::File(path) => std::fs::read(path).map_err(|err| {
+                Error::with_kind(
+                    ErrorKind::InvalidKey,
+                    &format!("failed to read the key from {} – {}", path.display(), err),
+                )
+                .with_source(err)
+            }),
+            Self::Env(name) => std::env::var(name).map(String::into_bytes).map_err(|err| {
+                Error::with_kind(
+                    ErrorKind::InvalidKey,
+                    &format!(
+                        "failed to read the key from the environment variable {} – {}",
+                        name, err
+                    ),
+                )
+                .with_source(err)
+            }),
+            Self::Base64(data) => STANDARD.decode(data.trim()).map_err(|err| {
+                Error::with_kind(
+                    ErrorKind::InvalidKey,
+                    &format!("invalid base64 encoded key – {}", err),
+                )
+                .with_source(err)
+            }),
+            Self::Inline(data) => Ok(data.as_bytes().to_owned()),
+        }
+    }
+}
+
+impl FromStr for KeySource {
+    type Err = Error;
+
+    fn from_str(val: &str) -> Result<Self, Self::Err> {
+        let source = match val.split_once(':') {
+            Some(("file", path)) => Self::File(PathBuf::from(path)),
+            Some(("env", name)) => Self::Env(name.to_owned()),
+            Some(("base64", data)) => Self::Base64(data.to_owned()),
+            Some(("inline", data)) => Self::Inline(data.to_owned()),
+            _ if val.starts_with("-----BEGIN ") => Self::Inline(val.to_owned()),
+            _ => Self::File(PathBuf::from(val)),
+        };
+
+        match source {
+            Self::File(ref path) if path.as_os_str().is_empty() => Err(Error::with_kind(
+                ErrorKind::InvalidKey,
+                "empty path to the key",
+            )),
+            Self::Env(ref name) if name.is_empty() => Err(Error::with_kind(
+                ErrorKind::InvalidKey,
+                "empty name of the environment variable",
+            )),
+            source => Ok(source),
+        }
+    }
+}
+
+impl fmt::Display for KeySource {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::File(path) => write!(fmt, "file:{}", path.display()),
+            Self::Env(name) => write!(fmt, "env:{}", name),
+            Self::Base64(_) => write!(fmt, "base64:***"),
+            Self::Inline(_) => write!(fmt, "inline:***"),
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_sources() {
+        let cases = [
+            ("/path/to/key", KeySource::File("/path/to/key".into())),
+            ("file:/path/to/key", KeySource::File("/path/to/key".into())),
+            ("env:AUTHN_KEY", KeySource::Env("AUTHN_KEY".to_owned())),
+            ("base64:c2VjcmV0", KeySource::Base64("c2VjcmV0".to_owned())),
+            ("inline:secret", KeySource::Inline("secret".to_owned())),
+            (
+                "-----BEGIN PUBLIC KEY-----",
+                KeySource::Inline("-----BEGIN PUBLIC KEY-----".to_owned()),
+            ),
+        ];
+
+        for (value, source) in cases.iter() {
+            assert_eq!(&KeySource::from_str(value).unwrap(), source);
+        }
+
+        assert!(KeySource::from_str("env:").is_err());
+        assert!(KeySource::from_str("").is_err());
+    }
+
+    #[test]
+    fn loads_keys() {
+        std::env::set_var("SVC_AUTHN_TEST_KEY", "secret");
+
+        let cases = ["env:SVC_AUTHN_TEST_KEY", "base64:c2VjcmV0", "inline:secret"];
+
+        for value in cases.iter() {
+            let source = KeySource::from_str(value).unwrap();
+            assert_eq!(source.load().unwrap(), b"secret");
+        }
+
+        let source = KeySource::from_str("file:data/keys/foo.public.der.example").unwrap();
+        assert_eq!(source.load().unwrap().len(), 65);
+    }
+}
@@ -0,0 +1,482 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, PoisonError, RwLock, Weak};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime};
+
+use super::{ConfigMap, KeySource};
+use crate::token::jws_compact::{ReplayCache, RevocationList, Verifier};
+use crate::{Error, ErrorKind};
+
+type Loader = dyn Fn() -> Result<(ConfigMap, Vec<PathBuf>), Error> + Send + Sync;
+type Files = Vec<(PathBuf, Option<SystemTime>)>;
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// A holder of the app config that may be reloaded while it's in use.
+///
+/// The config is loaded by the provided function and validated by preparing
+/// a verifier for it. The active config and verifier are replaced atomically,
+/// so callers holding the previous ones may complete their work. A config that
+/// fails to load or validate never replaces the active one.
+///
+/// A replay cache and a revocation list attached to the holder are kept
+/// by verifiers of reloaded configs.
+pub struct ReloadableConfigMap {
+    loader: Box<Loader>,
+    hooks: Hooks,
+    active: RwLock<(Arc<ConfigMap>, Arc<Verifier>)>,
+    files: Mutex<Files>,
+    key_files: Mutex<Files>,
+}
+
+impl ReloadableConfigMap {
+    pub fn new<F>(loader: F) -> Result<Self, Error>
+    where
+        F: Fn() -> Result<ConfigMap, Error> + Send + Sync + 'static,
+    {
+        Self::with_key_files(move || loader().map(|config| (config, Vec::new())))
+    }
+
+    fn with_key_files<F>(loader: F) -> Result<Self, Error>
+    where
+        F: Fn() -> Result<(ConfigMap, Vec<PathBuf>), Error> + Send + Sync + 'static,
+    {
+        let hooks = Hooks::default();
+        let (active, key_files) = load(&loader, &hooks)?;
+
+        Ok(Self {
+            loader: Box::new(loader),
+            hooks,
+            active: RwLock::new(active),
+            files: Mutex::new(Vec::new()),
+            key_files: Mutex::new(key_files),
+        })
+    }
+
+    /// Creates a holder of the config read from a JSON file which is also being watched
+    /// along with key files the config refers to.
+    pub fn from_json_file<P>(path: P) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref().to_owned();
+        let loader_path = path.clone();
+        let loader = move || {
+            let data = std::fs::read(&loader_path).map_err(|err| {
+                Error::with_kind(
+                    ErrorKind::InvalidConfig,
+                    &format!(
+                        "failed to read the config from {} – {}",
+                        loader_path.display(),
+                        err
+                    ),
+                )
+                .with_source(err)
+            })?;
+
+            let invalid = |err: serde_json::Error| {
+                Error::with_kind(
+                    ErrorKind::InvalidConfig,
+                    &format!("invalid config – {}", err),
+                )
+                .with_source(err)
+            };
+            let value = serde_json::from_slice::<serde_json::Value>(&data).map_err(invalid)?;
+            let key_files = key_files(&value);
+            let config = serde_json::from_value::<ConfigMap>(value).map_err(invalid)?;
+            Ok((config, key_files))
+        };
+
+        Ok(Self::with_key_files(loader)?.watch(path))
+    }
+
+    /// Makes tokens one-time, see [`Verifier::with_replay_cache`].
+    pub fn with_replay_cache(self, value: Arc<dyn ReplayCache>) -> Self {
+        let hooks = Hooks {
+            replay_cache: Some(value),
+            ..self.hooks.clone()
+        };
+        self.with_hooks(hooks)
+    }
+
+    /// Rejects revoked tokens, see [`Verifier::with_revocation_list`].
+    pub fn with_revocation_list(self, value: Arc<dyn RevocationList>) -> Self {
+        let hooks = Hooks {
+            revocation_list: Some(value),
+            ..self.hooks.clone()
+        };
+        self.with_hooks(hooks)
+    }
+
+    fn with_hooks(self, hooks: Hooks) -> Self {
+        {
+            let mut active = self.active.write().unwrap_or_else(PoisonError::into_inner);
+            active.1 = Arc::new(hooks.apply((*active.1).clone()));
+        }
+
+        Self { hooks, ..self }
+    }
+
+    /// Adds a file whose changes trigger reloading, e.g. a config or a key file.
+    pub fn watch<P>(self, path: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref().to_owned();
+        let modified = modified(&path);
+        self.files
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push((path, modified));
+        self
+    }
+
+    // The active config and verifier are always replaced as a whole,
+    // so they're still consistent when the lock is poisoned.
+    pub fn config(&self) -> Arc<ConfigMap> {
+        self.active
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .0
+            .clone()
+    }
+
+    pub fn verifier(&self) -> Arc<Verifier> {
+        self.active
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .1
+            .clone()
+    }
+
+    /// Loads the config and replaces the active one if it's valid.
+    pub fn reload(&self) -> Result<(), Error> {
+        let (active, key_files) = load(&self.loader, &self.hooks)?;
+        let mut active_lock = self.active.write().map_err(|_| poisoned("config"))?;
+        let mut key_files_lock = self.key_files.lock().map_err(|_| poisoned("key files"))?;
+        *active_lock = active;
+        *key_files_lock = key_files;
+        Ok(())
+    }
+
+    /// Reloads the config if any of the watched files has been modified since the last check.
+    ///
+    /// Returns `true` when the config has been replaced. Changes of an invalid config
+    /// are reported once, the next attempt is made when the files are modified again.
+    pub fn reload_if_modified(&self) -> Result<bool, Error> {
+        let changed = {
+            let mut files = self.files.lock().map_err(|_| poisoned("config files"))?;
+            let mut key_files = self.key_files.lock().map_err(|_| poisoned("key files"))?;
+            check_modified(&mut files) | check_modified(&mut key_files)
+        };
+
+        if changed {
+            self.reload().map(|()| true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    /// Spawns a thread checking the watched files with the specified interval.
+    ///
+    /// Errors of reloading are passed to the callback. The thread stops
+    /// once the holder is dropped.
+    pub fn spawn_watcher<F>(self: &Arc<Self>, interval: Duration, on_error: F) -> JoinHandle<()>
+    where
+        F: Fn(Error) + Send + 'static,
+    {
+        let holder: Weak<Self> = Arc::downgrade(self);
+        thread::spawn(move || loop {
+            thread::sleep(interval);
+
+            match holder.upgrade() {
+                Some(holder) => {
+                    if let Err(err) = holder.reload_if_modified() {
+                        on_error(err);
+                    }
+                }
+                None => break,
+            }
+        })
+    }
+}
+
+impl fmt::Debug for ReloadableConfigMap {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let files = self.files.lock().unwrap_or_else(PoisonError::into_inner);
+        let key_files = self
+            .key_files
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        fmt.debug_struct("ReloadableConfigMap")
+            .field("verifier", &self.verifier())
+            .field(
+                "files",
+                &files.iter().map(|(path, _)| path).collect::<Vec<_>>(),
+            )
+            .field(
+                "key_files",
+                &key_files.iter().map(|(path, _)| path).collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Clone, Default)]
+struct Hooks {
+    replay_cache: Option<Arc<dyn ReplayCache>>,
+    revocation_list: Option<Arc<dyn RevocationList>>,
+}
+
+impl Hooks {
+    fn apply(&self, verifier: Verifier) -> Verifier {
+        let verifier = match self.replay_cache {
+            Some(ref value) => verifier.with_replay_cache(value.clone()),
+            None => verifier,
+        };
+
+        match self.revocation_list {
+            Some(ref value) => verifier.with_revocation_list(value.clone()),
+            None => verifier,
+        }
+    }
+}
+
+type Loaded = ((Arc<ConfigMap>, Arc<Verifier>), Files);
+
+fn load(loader: &Loader, hooks: &Hooks) -> Result<Loaded, Error> {
+    let (config, key_files) = loader()?;
+    let verifier = hooks.apply(Verifier::new(&config)?);
+    let key_files = key_files
+        .into_iter()
+        .map(|path| {
+            let modified = modified(&path);
+            (path, modified)
+        })
+        .collect();
+    Ok(((Arc::new(config), Arc::new(verifier)), key_files))
+}
+
+/// Collects files of `key`, `jwks` and `keys[].key` sources of issuer configs.
+fn key_files(config: &serde_json::Value) -> Vec<PathBuf> {
+    let sources = config
+        .as_object()
+        .into_iter()
+        .flat_map(|issuers| issuers.values())
+        .flat_map(|issuer| {
+            let keys = issuer
+                .get("keys")
+                .and_then(serde_json::Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(|key| key.get("key"));
+            issuer
+                .get("key")
+                .into_iter()
+                .chain(issuer.get("jwks"))
+                .chain(keys)
+        });
+
+    let mut files = Vec::new();
+    for source in sources.filter_map(serde_json::Value::as_str) {
+        if let Ok(KeySource::File(path)) = KeySource::from_str(source) {
+            if !files.contains(&path) {
+                files.push(path);
+            }
+        }
+    }
+    files
+}
+
+fn check_modified(files: &mut Files) -> bool {
+    let mut changed = false;
+    for (path, last_modified) in files.iter_mut() {
+        let modified = modified(path);
+        if modified != *last_modified {
+            *last_modified = modified;
+            changed = true;
+        }
+    }
+    changed
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+fn poisoned(name: &str) -> Error {
+    Error::with_kind(ErrorKind::Internal, &format!("{} lock is poisoned", name))
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_config(path: &Path, key: &str) {
+        let config = serde_json::json!({
+            "example.org": {
+                "audience": ["example.org"],
+                "algorithm": "HS256",
+                "key": key,
+            }
+        });
+
+        std::fs::write(path, config.to_string()).unwrap();
+    }
+
+    #[test]
+    fn reloads_valid_config_only() {
+        let path =
+            std::env::temp_dir().join(format!("svc-authn-reload-{}.json", std::process::id()));
+        write_config(&path, "inline:old");
+
+        let holder = ReloadableConfigMap::from_json_file(&path).unwrap();
+        let previous = holder.config();
+        assert!(!holder.reload_if_modified().unwrap());
+
+        write_config(&path, "inline:new");
+        holder.reload().unwrap();
+        assert_eq!(holder.config()["example.org"].key(), b"new");
+        // The previously obtained config remains usable.
+        assert_eq!(previous["example.org"].key(), b"old");
+
+        std::fs::write(&path, "{").unwrap();
+        assert!(holder.reload().is_err());
+        assert_eq!(holder.config()["example.org"].key(), b"new");
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn survives_poisoned_locks() {
+        let holder = Arc::new(ReloadableConfigMap::new(|| Ok(ConfigMap::new())).unwrap());
+
+        let poisoner = holder.clone();
+        let _ = thread::spawn(move || {
+            let _active = poisoner.active.write().unwrap();
+            let _files = poisoner.files.lock().unwrap();
+            panic!("poisoning the locks");
+        })
+        .join();
+
+        assert!(holder.config().is_empty());
+        let _ = holder.verifier();
+        let _ = format!("{:?}", holder);
+
+        let err = holder.reload().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Internal);
+        let err = holder.reload_if_modified().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Internal);
+    }
+
+    #[test]
+    fn keeps_replay_cache_across_reloads() {
+        use crate::token::jws_compact::{InMemoryReplayCache, TokenBuilder};
+        use crate::{AccountId, ErrorKind};
+
+        let path =
+            std::env::temp_dir().join(format!("svc-authn-replay-{}.json", std::process::id()));
+        write_config(&path, "inline:secret");
+
+        let cache = InMemoryReplayCache::new(Duration::from_secs(60));
+        let holder = ReloadableConfigMap::from_json_file(&path)
+            .unwrap()
+            .with_replay_cache(Arc::new(cache));
+
+        let token = TokenBuilder::new()
+            .issuer("example.org")
+            .subject(&AccountId::new("john", "example.org"))
+            .generate_token_id()
+            .key(crate::jose::Algorithm::HS256, b"secret")
+            .build()
+            .unwrap();
+        assert!(holder.verifier().verify::<String>(&token).is_ok());
+
+        holder.reload().unwrap();
+        let err = holder.verifier().verify::<String>(&token).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Replayed);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn keeps_revocation_list_across_reloads() {
+        use crate::token::jws_compact::{token_hash, InMemoryRevocationList, TokenBuilder};
+        use crate::{AccountId, ErrorKind};
+
+        let path =
+            std::env::temp_dir().join(format!("svc-authn-revoke-{}.json", std::process::id()));
+        write_config(&path, "inline:secret");
+
+        let list = Arc::new(InMemoryRevocationList::new());
+        let holder = ReloadableConfigMap::from_json_file(&path)
+            .unwrap()
+            .with_revocation_list(list.clone());
+
+        let token = TokenBuilder::new()
+            .issuer("example.org")
+            .subject(&AccountId::new("john", "example.org"))
+            .key(crate::jose::Algorithm::HS256, b"secret")
+            .build()
+            .unwrap();
+        list.revoke_token_hash(&token_hash(&token));
+
+        holder.reload().unwrap();
+        let err = holder.verifier().verify::<String>(&token).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Revoked);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn reloads_modified_config_and_key_files() {
+        let dir = std::env::temp_dir();
+        let path = dir.join(format!("svc-authn-watch-{}.json", std::process::id()));
+        let key_path = dir.join(format!("svc-authn-watch-{}.key", std::process::id()));
+
+        // NOTE: modification times are set explicitly since their resolution may be coarse.
+        let touch = |path: &Path, secs: u64| {
+            std::fs::File::options()
+                .write(true)
+                .open(path)
+                .unwrap()
+                .set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(secs))
+                .unwrap();
+        };
+        let write = |path: &Path, data: &str, secs: u64| {
+            std::fs::write(path, data).unwrap();
+            touch(path, secs);
+        };
+
+        write(&key_path, "old", 1);
+        write_config(&path, &format!("file:{}", key_path.display()));
+        touch(&path, 1);
+        let holder = ReloadableConfigMap::from_json_file(&path).unwrap();
+        assert!(!holder.reload_if_modified().unwrap());
+
+        write(&key_path, "new", 2);
+        assert!(holder.reload_if_modified().unwrap());
+        assert_eq!(holder.config()["example.org"].key(), b"new");
+        assert!(!holder.reload_if_modified().unwrap());
+
+        write_config(&path, "inline:inline");
+        touch(&path, 2);
+        assert!(holder.reload_if_modified().unwrap());
+        assert_eq!(holder.config()["example.org"].key(), b"inline");
+
+        // The key file is no longer referenced by the config.
+        write(&key_path, "newer", 3);
+        assert!(!holder.reload_if_modified().unwrap());
+
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(&key_path).unwrap();
+    }
+}
@@ -14,17 +14,19 @@ impl fmt::Debug for &dyn Authenticable {
     }
 }
 
+#[cfg(feature = "axum")]
+pub mod axum;
 #[cfg(feature = "jose")]
 pub mod jose;
 
-pub use self::account::AccountId;
+pub use self::account::{AccountId, CrossAudience};
 #[cfg(feature = "diesel")]
 pub mod sql {
     pub use super::account::sql::Account_id;
 }
 mod account;
 
-pub use self::error::{Error, SerializationError};
+pub use self::error::{Error, ErrorKind, SerializationError};
 pub mod error;
 pub mod serde;
 pub mod token;
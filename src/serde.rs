@@ -36,4 +36,4 @@ where
 ////////////////////////////////////////////////////////////////////////////////
 
 #[cfg(feature = "jose")]
-pub use crate::jose::serde::algorithm;
+pub use crate::jose::serde::{algorithm, jwks, key};
@@ -26,22 +26,723 @@ impl Deref for Token {
 ////////////////////////////////////////////////////////////////////////////////
 
 pub mod extract {
-    use http::header::HeaderValue;
+    use crate::{Error, ErrorKind};
+
+    /// A maximum length of a token accepted by the parser.
+    pub const MAX_TOKEN_LENGTH: usize = 8192;
+
+    /// A header value of the `http` crate, either 0.2 or 1.x with the `http1` feature.
+    pub trait HeaderStr {
+        /// Returns the value if it only contains visible ASCII characters.
+        fn header_str(&self) -> Option<&str>;
+    }
+
+    /// A header map of the `http` crate, either 0.2 or 1.x with the `http1` feature.
+    pub trait HeaderValues {
+        type Value: HeaderStr;
+
+        /// Returns all values of the header in order.
+        fn header_values(&self, name: &str) -> Vec<&Self::Value>;
+    }
+
+    /// A request URI of the `http` crate, either 0.2 or 1.x with the `http1` feature.
+    pub trait QueryStr {
+        fn query_str(&self) -> Option<&str>;
+    }
+
+    macro_rules! impl_http_traits {
+        ($http:ident) => {
+            impl HeaderStr for $http::HeaderValue {
+                fn header_str(&self) -> Option<&str> {
+                    self.to_str().ok()
+                }
+            }
 
-    use crate::Error;
+            impl HeaderValues for $http::HeaderMap {
+                type Value = $http::HeaderValue;
 
-    pub fn parse_bearer_token(header: &HeaderValue) -> Result<&str, Error> {
-        let val: Vec<&str> = header
-            .to_str()
-            .map_err(|_| Error::new("invalid characters in the authorization header"))?
-            .split(' ')
-            .collect();
+                fn header_values(&self, name: &str) -> Vec<&Self::Value> {
+                    self.get_all(name).iter().collect()
+                }
+            }
 
-        match val[..] {
-            ["Bearer", val] => Ok(val),
-            _ => Err(Error::new(
+            impl QueryStr for $http::Uri {
+                fn query_str(&self) -> Option<&str> {
+                    self.query()
+                }
+            }
+        };
+    }
+
+    impl_http_traits!(http);
+    #[cfg(feature = "http1")]
+    impl_http_traits!(http1);
+
+    pub fn parse_bearer_token(header: &impl HeaderStr) -> Result<&str, Error> {
+        let value = header.header_str().ok_or_else(|| {
+            Error::with_kind(
+                ErrorKind::MalformedHeader,
+                "invalid characters in the authorization header",
+            )
+        })?;
+
+        parse_bearer_credentials(value)
+    }
+
+    /// Parses credentials of the Bearer authentication scheme (RFC 6750, section 2.1).
+    ///
+    /// The scheme name is case-insensitive (RFC 7235) and may be separated
+    /// from the token by any number of spaces or tabs. The token must be
+    /// a valid token68 no longer than [`MAX_TOKEN_LENGTH`].
+    pub fn parse_bearer_credentials(value: &str) -> Result<&str, Error> {
+        let is_whitespace = |c: char| c == ' ' || c == '\t';
+
+        let value = value.trim_matches(is_whitespace);
+        let (scheme, token) = match value.find(is_whitespace) {
+            Some(idx) => (
+                &value[..idx],
+                value[idx..].trim_start_matches(is_whitespace),
+            ),
+            None => (value, ""),
+        };
+
+        if !scheme.eq_ignore_ascii_case("Bearer") {
+            return Err(Error::with_kind(
+                ErrorKind::UnsupportedScheme,
                 "unsupported or invalid type of the authentication token",
+            ));
+        }
+
+        check_token(token, ErrorKind::MalformedHeader)
+    }
+
+    /// Reads the `access_token` parameter of the query string (RFC 6750, section 2.3).
+    pub fn parse_query_token(uri: &impl QueryStr) -> Result<Option<String>, Error> {
+        match uri.query_str() {
+            Some(query) => find_access_token(query.as_bytes()),
+            None => Ok(None),
+        }
+    }
+
+    /// Reads the `access_token` parameter of the form-encoded body (RFC 6750, section 2.2).
+    ///
+    /// Bodies of other content types are ignored.
+    pub fn parse_form_token(
+        headers: &impl HeaderValues,
+        body: &[u8],
+    ) -> Result<Option<String>, Error> {
+        let is_form = headers
+            .header_values(CONTENT_TYPE)
+            .first()
+            .and_then(|value| value.header_str())
+            .and_then(|value| value.split(';').next())
+            .map(|value| value.trim().eq_ignore_ascii_case(FORM_CONTENT_TYPE))
+            .unwrap_or(false);
+
+        if is_form {
+            find_access_token(body)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Reads the token from the authorization header, the query string or the form-encoded body.
+    ///
+    /// Exactly one of the methods must be used (RFC 6750, section 2).
+    /// An authorization header of another scheme isn't considered as a method.
+    pub fn parse_request_token(
+        headers: &impl HeaderValues,
+        uri: &impl QueryStr,
+        body: Option<&[u8]>,
+    ) -> Result<String, Error> {
+        let values = headers.header_values(AUTHORIZATION);
+        let header = match (values.first(), values.get(1)) {
+            (Some(_), Some(_)) => {
+                return Err(Error::with_kind(
+                    ErrorKind::MalformedHeader,
+                    "multiple authorization headers",
+                ))
+            }
+            (Some(value), None) => match parse_bearer_token(*value) {
+                Ok(token) => Some(token.to_owned()),
+                Err(ref err) if err.kind() == ErrorKind::UnsupportedScheme => None,
+                Err(err) => return Err(err),
+            },
+            (None, _) => None,
+        };
+
+        let query = parse_query_token(uri)?;
+        let form = match body {
+            Some(body) => parse_form_token(headers, body)?,
+            None => None,
+        };
+
+        let mut tokens = header.into_iter().chain(query).chain(form);
+        match (tokens.next(), tokens.next()) {
+            (Some(token), None) => Ok(token),
+            (Some(_), Some(_)) => Err(Error::with_kind(
+                ErrorKind::MalformedRequest,
+                "more than one method is used to pass the authentication token",
+            )),
+            (None, _) => Err(Error::with_kind(
+                ErrorKind::MissingToken,
+                "missing authentication token",
+            )),
+        }
+    }
+
+    /// Reads the token from the cookie with the given name (RFC 6265, section 4.2).
+    ///
+    /// All `Cookie` headers are considered, the first cookie with the name wins.
+    /// A value in double quotes is unquoted.
+    pub fn parse_cookie_token(
+        headers: &impl HeaderValues,
+        name: &str,
+    ) -> Result<Option<String>, Error> {
+        for header in headers.header_values(COOKIE) {
+            let header = header.header_str().ok_or_else(|| {
+                Error::with_kind(
+                    ErrorKind::MalformedHeader,
+                    "invalid characters in the cookie header",
+                )
+            })?;
+
+            let value = header
+                .split(';')
+                .filter_map(|pair| pair.trim().split_once('='))
+                .find(|(key, _)| key.trim() == name)
+                .map(|(_, value)| value.trim());
+
+            if let Some(value) = value {
+                let value = value
+                    .strip_prefix('"')
+                    .and_then(|value| value.strip_suffix('"'))
+                    .unwrap_or(value);
+                return check_token(value, ErrorKind::MalformedHeader)
+                    .map(|token| Some(token.to_owned()));
+            }
+        }
+
+        Ok(None)
+    }
+
+    const AUTHORIZATION: &str = "authorization";
+    const CONTENT_TYPE: &str = "content-type";
+    const COOKIE: &str = "cookie";
+    const ACCESS_TOKEN: &str = "access_token";
+    const FORM_CONTENT_TYPE: &str = "application/x-www-form-urlencoded";
+
+    fn find_access_token(input: &[u8]) -> Result<Option<String>, Error> {
+        let mut values = form_urlencoded::parse(input)
+            .filter(|(name, _)| name == ACCESS_TOKEN)
+            .map(|(_, value)| value);
+
+        match (values.next(), values.next()) {
+            (Some(_), Some(_)) => Err(Error::with_kind(
+                ErrorKind::MalformedRequest,
+                "multiple access_token parameters",
             )),
+            (Some(value), None) => {
+                check_token(&value, ErrorKind::MalformedRequest)?;
+                Ok(Some(value.into_owned()))
+            }
+            (None, _) => Ok(None),
+        }
+    }
+
+    fn check_token(token: &str, kind: ErrorKind) -> Result<&str, Error> {
+        if token.is_empty() {
+            return Err(Error::with_kind(kind, "missing authentication token"));
+        }
+
+        if token.len() > MAX_TOKEN_LENGTH {
+            return Err(Error::with_kind(
+                kind,
+                &format!(
+                    "the authentication token exceeds {} bytes",
+                    MAX_TOKEN_LENGTH
+                ),
+            ));
+        }
+
+        if !is_token68(token) {
+            return Err(Error::with_kind(
+                kind,
+                "invalid characters in the authentication token",
+            ));
+        }
+
+        Ok(token)
+    }
+
+    // token68 = 1*( ALPHA / DIGIT / "-" / "." / "_" / "~" / "+" / "/" ) *"="
+    fn is_token68(value: &str) -> bool {
+        let value = value.trim_end_matches('=');
+        !value.is_empty()
+            && value.bytes().all(|b| {
+                b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_' | b'~' | b'+' | b'/')
+            })
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+pub mod challenge {
+    use std::fmt;
+
+    use http::header::HeaderValue;
+    use http::StatusCode;
+
+    use crate::{Error, ErrorKind};
+
+    /// An error code of the Bearer authentication scheme (RFC 6750, section 3.1).
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum ChallengeError {
+        InvalidRequest,
+        InvalidToken,
+        InsufficientScope,
+    }
+
+    impl ChallengeError {
+        /// A status code of the response the error is supposed to be returned with.
+        pub fn status_code(self) -> StatusCode {
+            match self {
+                Self::InvalidRequest => StatusCode::BAD_REQUEST,
+                Self::InvalidToken => StatusCode::UNAUTHORIZED,
+                Self::InsufficientScope => StatusCode::FORBIDDEN,
+            }
+        }
+
+        /// A status code of `http` 1.x, see [`ChallengeError::status_code`].
+        #[cfg(feature = "http1")]
+        pub fn http1_status_code(self) -> http1::StatusCode {
+            match self {
+                Self::InvalidRequest => http1::StatusCode::BAD_REQUEST,
+                Self::InvalidToken => http1::StatusCode::UNAUTHORIZED,
+                Self::InsufficientScope => http1::StatusCode::FORBIDDEN,
+            }
+        }
+    }
+
+    impl fmt::Display for ChallengeError {
+        fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+            let value = match self {
+                Self::InvalidRequest => "invalid_request",
+                Self::InvalidToken => "invalid_token",
+                Self::InsufficientScope => "insufficient_scope",
+            };
+
+            fmt.write_str(value)
+        }
+    }
+
+    /// A value of the `WWW-Authenticate` header for the Bearer authentication scheme.
+    #[derive(Debug, Clone, Default)]
+    pub struct Challenge {
+        realm: Option<String>,
+        scope: Option<String>,
+        error: Option<ChallengeError>,
+        description: Option<String>,
+    }
+
+    impl Challenge {
+        pub fn new() -> Self {
+            Default::default()
+        }
+
+        /// Creates a challenge for a failure of authentication.
+        ///
+        /// Requests without supported authentication get no error code as RFC 6750 requires,
+        /// malformed requests get `invalid_request` and the rest get `invalid_token`.
+        ///
+        /// Failures of the server, see [`ErrorKind::is_server_error`], get neither an error code
+        /// nor a description, so that their details aren't disclosed. The caller is supposed
+        /// to respond with `5xx` instead of the status code of the challenge.
+        pub fn from_error(err: &Error) -> Self {
+            let error = match err.kind() {
+                kind if kind.is_server_error() => return Self::new(),
+                ErrorKind::UnsupportedScheme | ErrorKind::MissingToken => return Self::new(),
+                ErrorKind::MalformedHeader | ErrorKind::MalformedRequest => {
+                    ChallengeError::InvalidRequest
+                }
+                _ => ChallengeError::InvalidToken,
+            };
+
+            Self::new().error(error).description(&err.to_string())
+        }
+
+        pub fn realm(self, value: &str) -> Self {
+            Self {
+                realm: Some(value.to_owned()),
+                ..self
+            }
+        }
+
+        /// Sets a space-delimited list of scopes required to access the resource.
+        pub fn scope(self, value: &str) -> Self {
+            Self {
+                scope: Some(value.to_owned()),
+                ..self
+            }
+        }
+
+        pub fn error(self, value: ChallengeError) -> Self {
+            Self {
+                error: Some(value),
+                ..self
+            }
+        }
+
+        pub fn description(self, value: &str) -> Self {
+            Self {
+                description: Some(value.to_owned()),
+                ..self
+            }
+        }
+
+        /// A status code of the response, `401 Unauthorized` if there is no error code.
+        pub fn status_code(&self) -> StatusCode {
+            self.error
+                .map(ChallengeError::status_code)
+                .unwrap_or(StatusCode::UNAUTHORIZED)
+        }
+
+        pub fn to_header_value(&self) -> HeaderValue {
+            // NOTE: all the values are sanitized, so the header value is always valid.
+            HeaderValue::from_str(&self.to_string())
+                .unwrap_or_else(|_| HeaderValue::from_static("Bearer"))
+        }
+
+        /// A status code of `http` 1.x, see [`Challenge::status_code`].
+        #[cfg(feature = "http1")]
+        pub fn http1_status_code(&self) -> http1::StatusCode {
+            self.error
+                .map(ChallengeError::http1_status_code)
+                .unwrap_or(http1::StatusCode::UNAUTHORIZED)
+        }
+
+        /// A header value of `http` 1.x, see [`Challenge::to_header_value`].
+        #[cfg(feature = "http1")]
+        pub fn to_http1_header_value(&self) -> http1::HeaderValue {
+            http1::HeaderValue::from_str(&self.to_string())
+                .unwrap_or_else(|_| http1::HeaderValue::from_static("Bearer"))
+        }
+    }
+
+    impl fmt::Display for Challenge {
+        fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+            let mut params = Vec::with_capacity(4);
+            if let Some(ref value) = self.realm {
+                params.push(("realm", quote(value)));
+            }
+            if let Some(ref value) = self.scope {
+                params.push(("scope", quote(value)));
+            }
+            if let Some(value) = self.error {
+                params.push(("error", quote(&value.to_string())));
+            }
+            if let Some(ref value) = self.description {
+                params.push(("error_description", quote(value)));
+            }
+
+            fmt.write_str("Bearer")?;
+            for (idx, (name, value)) in params.iter().enumerate() {
+                let separator = if idx == 0 { " " } else { ", " };
+                write!(fmt, "{}{}={}", separator, name, value)?;
+            }
+
+            Ok(())
+        }
+    }
+
+    /// Returns a `WWW-Authenticate` header value for a failure of authentication.
+    pub fn challenge(realm: &str, err: &Error) -> HeaderValue {
+        Challenge::from_error(err).realm(realm).to_header_value()
+    }
+
+    /// Returns a `WWW-Authenticate` header value of `http` 1.x, see [`challenge`].
+    #[cfg(feature = "http1")]
+    pub fn http1_challenge(realm: &str, err: &Error) -> http1::HeaderValue {
+        Challenge::from_error(err)
+            .realm(realm)
+            .to_http1_header_value()
+    }
+
+    // Parameter values are restricted to %x20-21 / %x23-5B / %x5D-7E by RFC 6750,
+    // so quotes and backslashes are replaced rather than escaped.
+    fn quote(value: &str) -> String {
+        let value = value
+            .chars()
+            .map(|c| match c {
+                '"' => '\'',
+                '\\' => '/',
+                ' '..='~' => c,
+                _ => '-',
+            })
+            .collect::<String>();
+
+        format!("\"{}\"", value)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use http::StatusCode;
+    use proptest::prelude::*;
+
+    use super::challenge::*;
+    use super::extract::*;
+    use crate::{Error, ErrorKind};
+
+    #[test]
+    fn parses_bearer_credentials() {
+        for value in &[
+            "Bearer abc",
+            "bearer abc",
+            "BEARER abc",
+            "Bearer  abc",
+            "Bearer\tabc",
+            " Bearer abc ",
+        ] {
+            assert_eq!(parse_bearer_credentials(value).unwrap(), "abc");
+        }
+
+        let kind = |value: &str| parse_bearer_credentials(value).unwrap_err().kind();
+        assert_eq!(kind("Basic abc"), ErrorKind::UnsupportedScheme);
+        assert_eq!(kind("Bearerabc"), ErrorKind::UnsupportedScheme);
+        assert_eq!(kind("Bearer"), ErrorKind::MalformedHeader);
+        assert_eq!(kind("Bearer a b"), ErrorKind::MalformedHeader);
+        assert_eq!(kind("Bearer ==="), ErrorKind::MalformedHeader);
+        assert_eq!(kind("Bearer a=b"), ErrorKind::MalformedHeader);
+
+        let token = "a".repeat(MAX_TOKEN_LENGTH + 1);
+        assert_eq!(
+            kind(&format!("Bearer {}", token)),
+            ErrorKind::MalformedHeader
+        );
+    }
+
+    #[test]
+    fn parses_request_tokens() {
+        use http::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
+        use http::Uri;
+
+        let uri = |value: &str| value.parse::<Uri>().unwrap();
+        let mut form = HeaderMap::new();
+        form.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("application/x-www-form-urlencoded; charset=utf-8"),
+        );
+
+        assert_eq!(
+            parse_query_token(&uri("/a?x=1&access_token=abc%2B")).unwrap(),
+            Some("abc+".to_owned())
+        );
+        assert_eq!(parse_query_token(&uri("/a?x=1")).unwrap(), None);
+        assert_eq!(
+            parse_form_token(&form, b"access_token=abc").unwrap(),
+            Some("abc".to_owned())
+        );
+        assert_eq!(
+            parse_form_token(&HeaderMap::new(), b"access_token=abc").unwrap(),
+            None
+        );
+
+        let kind = |err: Error| err.kind();
+        assert_eq!(
+            kind(parse_query_token(&uri("/a?access_token=a&access_token=b")).unwrap_err()),
+            ErrorKind::MalformedRequest
+        );
+        assert_eq!(
+            kind(parse_query_token(&uri("/a?access_token=a%20b")).unwrap_err()),
+            ErrorKind::MalformedRequest
+        );
+
+        let mut headers = form.clone();
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer abc"));
+        assert_eq!(
+            parse_request_token(&headers, &uri("/a"), Some(b"x=1")).unwrap(),
+            "abc"
+        );
+        assert_eq!(
+            parse_request_token(&form, &uri("/a"), Some(b"access_token=abc")).unwrap(),
+            "abc"
+        );
+        assert_eq!(
+            kind(parse_request_token(&headers, &uri("/a?access_token=abc"), None).unwrap_err()),
+            ErrorKind::MalformedRequest
+        );
+        assert_eq!(
+            kind(
+                parse_request_token(&form, &uri("/a?access_token=a"), Some(b"access_token=b"))
+                    .unwrap_err()
+            ),
+            ErrorKind::MalformedRequest
+        );
+        assert_eq!(
+            kind(parse_request_token(&form, &uri("/a"), None).unwrap_err()),
+            ErrorKind::MissingToken
+        );
+
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Basic abc"));
+        assert_eq!(
+            parse_request_token(&headers, &uri("/a?access_token=abc"), None).unwrap(),
+            "abc"
+        );
+        headers.append(AUTHORIZATION, HeaderValue::from_static("Bearer abc"));
+        assert_eq!(
+            kind(parse_request_token(&headers, &uri("/a"), None).unwrap_err()),
+            ErrorKind::MalformedHeader
+        );
+    }
+
+    #[test]
+    fn parses_cookie_tokens() {
+        use http::header::{HeaderMap, HeaderValue, COOKIE};
+
+        let mut headers = HeaderMap::new();
+        assert_eq!(parse_cookie_token(&headers, "token").unwrap(), None);
+
+        headers.append(COOKIE, HeaderValue::from_static("a=1; b=2"));
+        headers.append(
+            COOKIE,
+            HeaderValue::from_static("c=3;token=\"abc\"; token=def"),
+        );
+        assert_eq!(
+            parse_cookie_token(&headers, "token").unwrap(),
+            Some("abc".to_owned())
+        );
+        assert_eq!(
+            parse_cookie_token(&headers, "b").unwrap(),
+            Some("2".to_owned())
+        );
+        assert_eq!(parse_cookie_token(&headers, "d").unwrap(), None);
+
+        headers.insert(COOKIE, HeaderValue::from_static("token=a,b"));
+        let err = parse_cookie_token(&headers, "token").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::MalformedHeader);
+    }
+
+    #[cfg(feature = "http1")]
+    #[test]
+    fn builds_http1_challenges() {
+        let err = Error::with_kind(ErrorKind::MalformedHeader, "invalid header");
+        let value = Challenge::from_error(&err).realm("example.org");
+        assert_eq!(value.http1_status_code(), http1::StatusCode::BAD_REQUEST);
+        assert_eq!(value.to_http1_header_value(), value.to_string());
+        assert_eq!(
+            http1_challenge("example.org", &err),
+            challenge("example.org", &err).to_str().unwrap()
+        );
+        assert_eq!(
+            ChallengeError::InsufficientScope.http1_status_code(),
+            http1::StatusCode::FORBIDDEN
+        );
+    }
+
+    #[cfg(feature = "http1")]
+    #[test]
+    fn parses_http1_requests() {
+        use http1::header::{HeaderMap, HeaderValue, AUTHORIZATION, COOKIE};
+        use http1::Uri;
+
+        let value = HeaderValue::from_static("Bearer abc");
+        assert_eq!(parse_bearer_token(&value).unwrap(), "abc");
+
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, value);
+        headers.insert(COOKIE, HeaderValue::from_static("token=def"));
+        assert_eq!(
+            parse_request_token(&headers, &Uri::from_static("/a"), None).unwrap(),
+            "abc"
+        );
+        assert_eq!(
+            parse_cookie_token(&headers, "token").unwrap(),
+            Some("def".to_owned())
+        );
+        assert_eq!(
+            parse_query_token(&Uri::from_static("/a?access_token=ghi")).unwrap(),
+            Some("ghi".to_owned())
+        );
+    }
+
+    fn scheme() -> impl Strategy<Value = String> {
+        "[bB][eE][aA][rR][eE][rR]"
+    }
+
+    proptest! {
+        #[test]
+        fn accepts_token68(
+            scheme in scheme(),
+            separator in "[ \t]{1,4}",
+            token in "[A-Za-z0-9._~+/-]{1,512}={0,2}",
+        ) {
+            let value = format!("{}{}{}", scheme, separator, token);
+            prop_assert_eq!(parse_bearer_credentials(&value).unwrap(), token.as_str());
+        }
+
+        #[test]
+        fn rejects_non_token68(
+            prefix in "[A-Za-z0-9]{0,16}",
+            invalid in "[^A-Za-z0-9._~+/= \t-]",
+            suffix in "[A-Za-z0-9]{0,16}",
+        ) {
+            let value = format!("Bearer {}{}{}", prefix, invalid, suffix);
+            let err = parse_bearer_credentials(&value).unwrap_err();
+            prop_assert_eq!(err.kind(), ErrorKind::MalformedHeader);
         }
+
+        #[test]
+        fn never_panics(value in ".*") {
+            let _ = parse_bearer_credentials(&value);
+        }
+    }
+
+    #[test]
+    fn builds_challenges() {
+        let err = Error::with_kind(
+            ErrorKind::Expired,
+            "verification of the authentication token failed – ExpiredSignature",
+        );
+        let value = Challenge::from_error(&err).realm("example.org");
+        assert_eq!(value.status_code(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            value.to_header_value(),
+            "Bearer realm=\"example.org\", error=\"invalid_token\", error_description=\"verification of the authentication token failed - ExpiredSignature\""
+        );
+
+        let err = Error::with_kind(ErrorKind::MalformedHeader, "invalid \"header\"");
+        let value = challenge("example.org", &err);
+        assert_eq!(
+            value,
+            "Bearer realm=\"example.org\", error=\"invalid_request\", error_description=\"invalid 'header'\""
+        );
+
+        let err = Error::with_kind(ErrorKind::UnsupportedScheme, "unsupported");
+        assert_eq!(
+            challenge("example.org", &err),
+            "Bearer realm=\"example.org\""
+        );
+
+        for kind in &[ErrorKind::InvalidKey, ErrorKind::Internal, ErrorKind::Other] {
+            let err = Error::with_kind(*kind, "failed to read the key from /etc/keys/iam.pem");
+            assert!(kind.is_server_error());
+            assert_eq!(
+                challenge("example.org", &err),
+                "Bearer realm=\"example.org\""
+            );
+        }
+        assert!(!ErrorKind::Expired.is_server_error());
+
+        let value = Challenge::new()
+            .scope("read write")
+            .error(ChallengeError::InsufficientScope);
+        assert_eq!(value.status_code(), StatusCode::FORBIDDEN);
+        assert_eq!(
+            value.to_string(),
+            "Bearer scope=\"read write\", error=\"insufficient_scope\""
+        );
     }
 }
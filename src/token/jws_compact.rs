@@ -1,20 +1,35 @@
 use chrono::{Duration, Utc};
 use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
 
-use crate::jose::{Claims, ConfigMap};
-use crate::SerializationError;
-use crate::{AccountId, Authenticable};
+use crate::jose::{der, Claims, ConfigMap};
+use crate::{AccountId, Authenticable, CrossAudience};
+use crate::{ErrorKind, SerializationError};
+
+pub use self::replay::{InMemoryReplayCache, ReplayCache};
+pub use self::revocation::{
+    token_hash, FileRevocationList, InMemoryRevocationList, RevocationCheck, RevocationList,
+};
+pub use self::verifier::Verifier;
+mod replay;
+mod revocation;
+mod verifier;
 
 ////////////////////////////////////////////////////////////////////////////////
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct TokenBuilder<'a> {
     issuer: Option<&'a str>,
     subject: Option<&'a AccountId>,
+    target_audience: Option<&'a str>,
 
     expires_in: Option<i64>,
+    not_before: Option<u64>,
+    token_id: Option<&'a str>,
+    generate_token_id: bool,
+    extra_claims: Vec<(&'a str, serde_json::Value)>,
     algorithm: Option<Algorithm>,
     key: Option<&'a [u8]>,
+    key_id: Option<&'a str>,
 }
 
 impl<'a> TokenBuilder<'a> {
@@ -25,10 +40,7 @@ impl<'a> TokenBuilder<'a> {
     pub fn issuer(self, value: &'a str) -> Self {
         Self {
             issuer: Some(value),
-            subject: self.subject,
-            expires_in: self.expires_in,
-            algorithm: self.algorithm,
-            key: self.key,
+            ..self
         }
     }
 
@@ -37,84 +49,187 @@ impl<'a> TokenBuilder<'a> {
         A: Authenticable,
     {
         Self {
-            issuer: self.issuer,
             subject: Some(value.as_account_id()),
-            expires_in: self.expires_in,
-            algorithm: self.algorithm,
-            key: self.key,
+            ..self
+        }
+    }
+
+    /// Issues a cross-audience token for services of the target audience.
+    pub fn target_audience(self, value: &'a str) -> Self {
+        Self {
+            target_audience: Some(value),
+            ..self
         }
     }
 
     pub fn expires_in(self, value: i64) -> Self {
         Self {
-            issuer: self.issuer,
-            subject: self.subject,
             expires_in: Some(value),
-            algorithm: self.algorithm,
-            key: self.key,
+            ..self
+        }
+    }
+
+    /// Sets a time as a Unix timestamp before which the token must not be accepted.
+    pub fn not_before(self, value: u64) -> Self {
+        Self {
+            not_before: Some(value),
+            ..self
         }
     }
 
+    pub fn token_id(self, value: &'a str) -> Self {
+        Self {
+            token_id: Some(value),
+            ..self
+        }
+    }
+
+    /// Puts a random unique identifier into the token unless it's set explicitly.
+    pub fn generate_token_id(self) -> Self {
+        Self {
+            generate_token_id: true,
+            ..self
+        }
+    }
+
+    /// Adds a private claim, e.g. a tenant identifier.
+    pub fn claim(mut self, name: &'a str, value: serde_json::Value) -> Self {
+        self.extra_claims.push((name, value));
+        self
+    }
+
     pub fn key(self, algorithm: Algorithm, key: &'a [u8]) -> Self {
         Self {
-            issuer: self.issuer,
-            subject: self.subject,
-            expires_in: self.expires_in,
             algorithm: Some(algorithm),
             key: Some(key),
+            ..self
+        }
+    }
+
+    pub fn key_id(self, value: &'a str) -> Self {
+        Self {
+            key_id: Some(value),
+            ..self
         }
     }
 
     pub fn build(self) -> Result<String, SerializationError> {
-        let issuer = self
-            .issuer
-            .ok_or_else(|| SerializationError::new("invalid issuer"))?;
-        let subject = self
-            .subject
-            .ok_or_else(|| SerializationError::new("missing subject"))?;
-        let algorithm = self
-            .algorithm
-            .ok_or_else(|| SerializationError::new("missing algorithm"))?;
+        let issuer = self.issuer.ok_or_else(|| {
+            SerializationError::with_kind(ErrorKind::MissingClaim, "invalid issuer")
+        })?;
+        let subject = self.subject.ok_or_else(|| {
+            SerializationError::with_kind(ErrorKind::MissingClaim, "missing subject")
+        })?;
+        let algorithm = self.algorithm.ok_or_else(|| {
+            SerializationError::with_kind(ErrorKind::UnsupportedAlgorithm, "missing algorithm")
+        })?;
         let key = self
             .key
-            .ok_or_else(|| SerializationError::new("missing key"))?;
+            .ok_or_else(|| SerializationError::with_kind(ErrorKind::InvalidKey, "missing key"))?;
 
-        let mut claims = Claims::new(
-            issuer,
-            subject.as_account_id().audience(),
-            subject.as_account_id().label(),
-        );
+        let audience = match self.target_audience {
+            Some(target) => {
+                CrossAudience::new(subject.as_account_id().audience(), Some(target)).to_string()
+            }
+            None => subject.as_account_id().audience().to_owned(),
+        };
+        let mut claims = Claims::new(issuer, &audience, subject.as_account_id().label());
+
+        let now = Utc::now();
+        claims.set_issued_at(now.timestamp() as u64);
 
         if let Some(value) = self.expires_in {
-            claims.set_expiration_time((Utc::now() + Duration::seconds(value)).timestamp() as u64);
+            claims.set_expiration_time((now + Duration::seconds(value)).timestamp() as u64);
         }
 
-        let encoding_key = match algorithm {
-            Algorithm::HS256 => Ok(EncodingKey::from_secret(key)),
-            Algorithm::ES256 => Ok(EncodingKey::from_ec_der(key)),
-            _ => Err(SerializationError::new(&format!(
-                "unsupported algorithm {:?}",
-                algorithm,
-            ))),
-        }?;
+        if let Some(value) = self.not_before {
+            claims.set_not_before(value);
+        }
 
-        encode(&Header::new(algorithm), &claims, &encoding_key)
-            .map_err(|e| SerializationError::new(&format!("encoding error, {}", e)))
+        match self.token_id {
+            Some(value) => {
+                claims.set_token_id(value);
+            }
+            None if self.generate_token_id => {
+                claims.set_token_id(&random_token_id()?);
+            }
+            None => (),
+        }
+
+        for (name, value) in self.extra_claims {
+            claims.set_extra_claim(name, value).map_err(|e| {
+                SerializationError::with_kind(
+                    ErrorKind::InvalidClaim,
+                    &format!("invalid claim, {}", e),
+                )
+                .with_source(e)
+            })?;
+        }
+
+        let encoding_key = encoding_key(key, algorithm)?;
+
+        let mut header = Header::new(algorithm);
+        header.kid = self.key_id.map(ToOwned::to_owned);
+
+        encode(&header, &claims, &encoding_key)
+            .map_err(|e| SerializationError::new(&format!("encoding error, {}", e)).with_source(e))
     }
 }
 
+fn random_token_id() -> Result<String, SerializationError> {
+    use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    use base64::Engine;
+
+    let mut value = [0; 16];
+    getrandom::getrandom(&mut value).map_err(|e| {
+        SerializationError::with_kind(
+            ErrorKind::Internal,
+            &format!("failed to generate a token id, {}", e),
+        )
+    })?;
+    Ok(URL_SAFE_NO_PAD.encode(value))
+}
+
+fn encoding_key(key: &[u8], algorithm: Algorithm) -> Result<EncodingKey, SerializationError> {
+    let key = der::private_key(key, algorithm).map_err(|e| {
+        SerializationError::with_kind(ErrorKind::InvalidKey, &format!("invalid key, {}", e))
+            .with_source(e)
+    })?;
+
+    let encoding_key = match algorithm {
+        Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => EncodingKey::from_secret(&key),
+        Algorithm::ES256 | Algorithm::ES384 => EncodingKey::from_ec_der(&key),
+        Algorithm::RS256
+        | Algorithm::RS384
+        | Algorithm::RS512
+        | Algorithm::PS256
+        | Algorithm::PS384
+        | Algorithm::PS512 => EncodingKey::from_rsa_der(&key),
+        Algorithm::EdDSA => EncodingKey::from_ed_der(&key),
+    };
+
+    Ok(encoding_key)
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 
 pub mod extract {
-    use http::header::HeaderValue;
+    use std::collections::HashSet;
+
     use jsonwebtoken::{decode, Algorithm, DecodingKey, TokenData, Validation};
+    use serde::de::IgnoredAny;
 
-    use super::{Claims, ConfigMap};
-    use crate::token::bearer::extract::parse_bearer_token;
-    use crate::Error;
+    use super::verifier::IssuerVerifier;
+    use super::{Claims, ConfigMap, Verifier};
+    use crate::jose::der;
+    use crate::token::bearer::extract::{
+        parse_bearer_token, parse_cookie_token, parse_form_token, parse_query_token,
+        parse_request_token, HeaderStr, HeaderValues, QueryStr,
+    };
+    use crate::{Error, ErrorKind};
 
     pub fn extract_jws_compact<T>(
-        header: &HeaderValue,
+        header: &impl HeaderStr,
         authn: &ConfigMap,
     ) -> Result<TokenData<Claims<T>>, Error>
     where
@@ -124,6 +239,121 @@ pub mod extract {
             .and_then(|token| decode_jws_compact_with_config::<T>(token, authn))
     }
 
+    pub fn extract_jws_compact_with_verifier<T>(
+        header: &impl HeaderStr,
+        verifier: &Verifier,
+    ) -> Result<TokenData<Claims<T>>, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        parse_bearer_token(header).and_then(|token| verifier.verify::<T>(token))
+    }
+
+    /// Extracts the token from the `access_token` query parameter.
+    pub fn extract_jws_compact_from_query<T>(
+        uri: &impl QueryStr,
+        authn: &ConfigMap,
+    ) -> Result<TokenData<Claims<T>>, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let token = parse_query_token(uri)?.ok_or_else(missing_token)?;
+        decode_jws_compact_with_config::<T>(&token, authn)
+    }
+
+    pub fn extract_jws_compact_from_query_with_verifier<T>(
+        uri: &impl QueryStr,
+        verifier: &Verifier,
+    ) -> Result<TokenData<Claims<T>>, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let token = parse_query_token(uri)?.ok_or_else(missing_token)?;
+        verifier.verify::<T>(&token)
+    }
+
+    /// Extracts the token from the `access_token` parameter of the form-encoded body.
+    pub fn extract_jws_compact_from_form<T>(
+        headers: &impl HeaderValues,
+        body: &[u8],
+        authn: &ConfigMap,
+    ) -> Result<TokenData<Claims<T>>, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let token = parse_form_token(headers, body)?.ok_or_else(missing_token)?;
+        decode_jws_compact_with_config::<T>(&token, authn)
+    }
+
+    pub fn extract_jws_compact_from_form_with_verifier<T>(
+        headers: &impl HeaderValues,
+        body: &[u8],
+        verifier: &Verifier,
+    ) -> Result<TokenData<Claims<T>>, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let token = parse_form_token(headers, body)?.ok_or_else(missing_token)?;
+        verifier.verify::<T>(&token)
+    }
+
+    /// Extracts the token from the cookie with the given name.
+    pub fn extract_jws_compact_from_cookie<T>(
+        headers: &impl HeaderValues,
+        name: &str,
+        authn: &ConfigMap,
+    ) -> Result<TokenData<Claims<T>>, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let token = parse_cookie_token(headers, name)?.ok_or_else(missing_token)?;
+        decode_jws_compact_with_config::<T>(&token, authn)
+    }
+
+    pub fn extract_jws_compact_from_cookie_with_verifier<T>(
+        headers: &impl HeaderValues,
+        name: &str,
+        verifier: &Verifier,
+    ) -> Result<TokenData<Claims<T>>, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let token = parse_cookie_token(headers, name)?.ok_or_else(missing_token)?;
+        verifier.verify::<T>(&token)
+    }
+
+    /// Extracts the token from whichever of the authorization header, the query string
+    /// or the form-encoded body carries it. Requests using more than one of them are rejected.
+    pub fn extract_jws_compact_from_request<T>(
+        headers: &impl HeaderValues,
+        uri: &impl QueryStr,
+        body: Option<&[u8]>,
+        authn: &ConfigMap,
+    ) -> Result<TokenData<Claims<T>>, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let token = parse_request_token(headers, uri, body)?;
+        decode_jws_compact_with_config::<T>(&token, authn)
+    }
+
+    pub fn extract_jws_compact_from_request_with_verifier<T>(
+        headers: &impl HeaderValues,
+        uri: &impl QueryStr,
+        body: Option<&[u8]>,
+        verifier: &Verifier,
+    ) -> Result<TokenData<Claims<T>>, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let token = parse_request_token(headers, uri, body)?;
+        verifier.verify::<T>(&token)
+    }
+
+    /// Verifies the token with the config of its issuer.
+    ///
+    /// Replay and revocation checks are only made by a [`Verifier`],
+    /// use `_with_verifier` variants of extractors to have them.
     pub fn decode_jws_compact_with_config<T>(
         token: &str,
         authn: &ConfigMap,
@@ -131,33 +361,18 @@ pub mod extract {
     where
         T: serde::de::DeserializeOwned,
     {
-        let parts = parse_jws_compact::<T>(token)?;
+        let parts = parse_jws_compact::<IgnoredAny>(token)?;
         let config = authn.get(parts.claims.issuer()).ok_or_else(|| {
-            Error::new(&format!(
-                "issuer = {} of the authentication token is not allowed",
-                parts.claims.issuer(),
-            ))
+            Error::with_kind(
+                ErrorKind::UnknownIssuer,
+                &format!(
+                    "issuer = {} of the authentication token is not allowed",
+                    parts.claims.issuer(),
+                ),
+            )
         })?;
 
-        // NOTE: we consider the token valid if its audience matches at least
-        // one audience from the app config for the same issuer.
-        // We can't use 'verifier.set_audience(&config.audience)' because it's
-        // succeed if only all values from the config represented in the token.
-        //
-        // If audience is in format '{audience1}:{audience2}' we check first audience
-        // Split will always return at least one part
-        let audience = parts.claims.audience().split(':').next().unwrap();
-        if !config.audience().contains(audience) {
-            return Err(Error::new(&format!(
-                "audience = {} of the authentication token is not allowed",
-                parts.claims.audience(),
-            )));
-        }
-
-        let mut verifier = Validation::new(config.algorithm());
-        verifier.validate_exp = parts.claims.expiration_time().is_some();
-
-        decode_jws_compact(token, &verifier, config.key().as_ref(), config.algorithm())
+        IssuerVerifier::for_token(config, &parts.header)?.verify(token, &parts)
     }
 
     pub fn decode_jws_compact<T>(
@@ -169,28 +384,809 @@ pub mod extract {
     where
         T: serde::de::DeserializeOwned,
     {
-        let decoding_key = match algorithm {
-            Algorithm::HS256 => Ok(DecodingKey::from_secret(key)),
-            Algorithm::ES256 => Ok(DecodingKey::from_ec_der(key)),
-            _ => Err(Error::new(&format!(
-                "unsupported algorithm {:?}",
-                algorithm
-            ))),
-        }?;
-
-        decode(token, &decoding_key, verifier).map_err(|err| {
-            Error::new(&format!(
-                "verification of the authentication token failed – {}",
-                &err,
-            ))
-        })
+        // NOTE: jsonwebtoken 8 requires 'exp' regardless of 'validate_exp',
+        // we keep the semantics of 7.x: a claim is required only if it's validated.
+        let mut verifier = verifier.clone();
+        for (claim, validate) in &[
+            ("exp", verifier.validate_exp),
+            ("nbf", verifier.validate_nbf),
+        ] {
+            if *validate {
+                verifier.required_spec_claims.insert((*claim).to_owned());
+            } else {
+                verifier.required_spec_claims.remove(*claim);
+            }
+        }
+
+        let decoding_key = decoding_key(key, algorithm)?;
+        decode(token, &decoding_key, &verifier).map_err(verification_error)
     }
 
     pub fn parse_jws_compact<T>(token: &str) -> Result<TokenData<Claims<T>>, Error>
     where
         T: serde::de::DeserializeOwned,
     {
-        jsonwebtoken::dangerous_insecure_decode(token)
-            .map_err(|_| Error::new("invalid claims of the authentication token"))
+        let mut verifier = Validation::default();
+        verifier.insecure_disable_signature_validation();
+        verifier.required_spec_claims = HashSet::new();
+        verifier.validate_exp = false;
+
+        decode(token, &DecodingKey::from_secret(&[]), &verifier).map_err(|err| {
+            Error::with_kind(
+                ErrorKind::MalformedToken,
+                "invalid claims of the authentication token",
+            )
+            .with_source(err)
+        })
+    }
+
+    fn missing_token() -> Error {
+        Error::with_kind(ErrorKind::MissingToken, "missing authentication token")
+    }
+
+    pub(crate) fn decoding_key(key: &[u8], algorithm: Algorithm) -> Result<DecodingKey, Error> {
+        let key = der::public_key(key, algorithm)?;
+
+        let decoding_key = match algorithm {
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => {
+                DecodingKey::from_secret(&key)
+            }
+            Algorithm::ES256 | Algorithm::ES384 => DecodingKey::from_ec_der(&key),
+            Algorithm::RS256
+            | Algorithm::RS384
+            | Algorithm::RS512
+            | Algorithm::PS256
+            | Algorithm::PS384
+            | Algorithm::PS512 => DecodingKey::from_rsa_der(&key),
+            Algorithm::EdDSA => DecodingKey::from_ed_der(&key),
+        };
+
+        Ok(decoding_key)
+    }
+
+    pub(crate) fn verification_error(err: jsonwebtoken::errors::Error) -> Error {
+        use jsonwebtoken::errors::ErrorKind as JwtErrorKind;
+
+        let kind = match err.kind() {
+            JwtErrorKind::InvalidSignature => ErrorKind::InvalidSignature,
+            JwtErrorKind::ExpiredSignature => ErrorKind::Expired,
+            JwtErrorKind::ImmatureSignature => ErrorKind::NotYetValid,
+            JwtErrorKind::InvalidAlgorithm | JwtErrorKind::InvalidAlgorithmName => {
+                ErrorKind::UnsupportedAlgorithm
+            }
+            JwtErrorKind::InvalidEcdsaKey
+            | JwtErrorKind::InvalidRsaKey(_)
+            | JwtErrorKind::InvalidKeyFormat => ErrorKind::InvalidKey,
+            JwtErrorKind::MissingRequiredClaim(_) => ErrorKind::MissingClaim,
+            JwtErrorKind::InvalidIssuer => ErrorKind::UnknownIssuer,
+            JwtErrorKind::InvalidAudience => ErrorKind::DisallowedAudience,
+            JwtErrorKind::InvalidToken
+            | JwtErrorKind::Base64(_)
+            | JwtErrorKind::Json(_)
+            | JwtErrorKind::Utf8(_) => ErrorKind::MalformedToken,
+            _ => ErrorKind::Other,
+        };
+
+        Error::with_kind(
+            kind,
+            &format!("verification of the authentication token failed – {}", err),
+        )
+        .with_source(err)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::extract::{decode_jws_compact_with_config, parse_jws_compact};
+    use super::*;
+    use crate::jose::{Config, Key, Policy};
+
+    fn config_map() -> ConfigMap {
+        config_map_with(vec![
+            Key::new(Some("2"), Algorithm::HS256, b"new"),
+            Key::new(Some("1"), Algorithm::HS256, b"old"),
+        ])
+    }
+
+    fn config_map_with(keys: Vec<Key>) -> ConfigMap {
+        let audience = vec!["example.org".to_owned()]
+            .into_iter()
+            .collect::<HashSet<_>>();
+
+        let mut authn = ConfigMap::new();
+        authn.insert(
+            "iam.example.org".to_owned(),
+            Config::new(audience, keys).unwrap(),
+        );
+        authn
+    }
+
+    fn build_token(key_id: Option<&str>, key: &[u8]) -> String {
+        let account = AccountId::new("john", "example.org");
+        let builder = TokenBuilder::new()
+            .issuer("iam.example.org")
+            .subject(&account)
+            .key(Algorithm::HS256, key);
+
+        match key_id {
+            Some(key_id) => builder.key_id(key_id),
+            None => builder,
+        }
+        .build()
+        .unwrap()
+    }
+
+    #[test]
+    fn selects_key_by_kid() {
+        let authn = config_map();
+
+        let token = build_token(Some("1"), b"old");
+        let data = decode_jws_compact_with_config::<String>(&token, &authn).unwrap();
+        assert_eq!(data.header.kid.as_deref(), Some("1"));
+        assert_eq!(data.claims.subject(), "john");
+
+        let token = build_token(Some("2"), b"old");
+        assert!(decode_jws_compact_with_config::<String>(&token, &authn).is_err());
+    }
+
+    #[test]
+    fn parses_only_selected_keys() {
+        let authn = config_map_with(vec![
+            Key::new(Some("2"), Algorithm::RS256, b"-----BEGIN PUBLIC KEY-----"),
+            Key::new(Some("1"), Algorithm::HS256, b"old"),
+        ]);
+
+        for kid in [Some("1"), None].iter() {
+            let token = build_token(*kid, b"old");
+            assert!(decode_jws_compact_with_config::<String>(&token, &authn).is_ok());
+        }
+
+        let err = Verifier::new(&authn).unwrap_err();
+        assert_eq!(err.kind(), crate::ErrorKind::InvalidConfig);
+    }
+
+    #[test]
+    fn tries_all_keys_without_kid() {
+        let authn = config_map();
+
+        let token = build_token(None, b"old");
+        assert!(decode_jws_compact_with_config::<String>(&token, &authn).is_ok());
+
+        let token = build_token(None, b"unknown");
+        assert!(decode_jws_compact_with_config::<String>(&token, &authn).is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_kid() {
+        let authn = config_map();
+
+        let token = build_token(Some("3"), b"new");
+        assert!(decode_jws_compact_with_config::<String>(&token, &authn).is_err());
+    }
+
+    #[test]
+    fn enforces_policy() {
+        let config = serde_json::from_value::<Config>(serde_json::json!({
+            "audience": ["example.org"],
+            "algorithm": "HS256",
+            "key": "inline:new",
+            "policy": { "max_lifetime": 60 },
+        }))
+        .unwrap();
+        let mut authn = ConfigMap::new();
+        authn.insert("iam.example.org".to_owned(), config.clone());
+
+        let account = AccountId::new("john", "example.org");
+        let builder = TokenBuilder::new()
+            .issuer("iam.example.org")
+            .subject(&account)
+            .key(Algorithm::HS256, b"new");
+
+        let token = builder.clone().build().unwrap();
+        let err = decode_jws_compact_with_config::<String>(&token, &authn).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "missing 'exp' claim of the authentication token"
+        );
+
+        let token = builder.clone().expires_in(3600).build().unwrap();
+        let err = decode_jws_compact_with_config::<String>(&token, &authn).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "lifetime of the authentication token exceeds 60 seconds"
+        );
+
+        let token = builder.expires_in(30).build().unwrap();
+        assert!(decode_jws_compact_with_config::<String>(&token, &authn).is_ok());
+
+        let policy = Policy::new().require_token_id(true);
+        authn.insert("iam.example.org".to_owned(), config.with_policy(policy));
+        let err = decode_jws_compact_with_config::<String>(&token, &authn).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "missing 'jti' claim of the authentication token"
+        );
+    }
+
+    #[test]
+    fn validates_not_before() {
+        let authn = config_map();
+
+        let now = Utc::now().timestamp() as u64;
+        let account = AccountId::new("john", "example.org");
+        let builder = TokenBuilder::new()
+            .issuer("iam.example.org")
+            .subject(&account)
+            .key(Algorithm::HS256, b"new");
+
+        let token = builder.clone().not_before(now + 3600).build().unwrap();
+        assert!(decode_jws_compact_with_config::<String>(&token, &authn).is_err());
+
+        let token = builder.not_before(now - 10).build().unwrap();
+        let data = decode_jws_compact_with_config::<String>(&token, &authn).unwrap();
+        assert_eq!(data.claims.not_before(), Some(now - 10));
+        assert!(data.claims.issued_at().unwrap() >= now);
+    }
+
+    #[test]
+    fn reports_error_kinds() {
+        use std::error::Error as _;
+
+        let authn = config_map();
+
+        let err = decode_jws_compact_with_config::<String>("invalid", &authn).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::MalformedToken);
+
+        let token = build_token(Some("3"), b"new");
+        let err = decode_jws_compact_with_config::<String>(&token, &authn).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnknownKey);
+
+        let token = build_token(Some("1"), b"new");
+        let err = decode_jws_compact_with_config::<String>(&token, &authn).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidSignature);
+        assert_eq!(
+            err.to_string(),
+            "verification of the authentication token failed – InvalidSignature"
+        );
+        assert!(err
+            .source()
+            .unwrap()
+            .downcast_ref::<jsonwebtoken::errors::Error>()
+            .is_some());
+
+        let account = AccountId::new("john", "example.org");
+        let token = TokenBuilder::new()
+            .issuer("iam.example.net")
+            .subject(&account)
+            .key(Algorithm::HS256, b"new")
+            .build()
+            .unwrap();
+        let err = decode_jws_compact_with_config::<String>(&token, &authn).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnknownIssuer);
+
+        let token = TokenBuilder::new()
+            .issuer("iam.example.org")
+            .subject(&account)
+            .expires_in(-10)
+            .key(Algorithm::HS256, b"new")
+            .build()
+            .unwrap();
+        let err = decode_jws_compact_with_config::<String>(&token, &authn).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Expired);
+    }
+
+    #[test]
+    fn requires_only_validated_claims() {
+        use super::extract::decode_jws_compact;
+
+        let token = build_token(None, b"old");
+
+        let mut validation = jsonwebtoken::Validation::new(Algorithm::HS256);
+        let err = decode_jws_compact::<String>(&token, &validation, b"old", Algorithm::HS256)
+            .unwrap_err();
+        assert_eq!(err.kind(), crate::ErrorKind::MissingClaim);
+
+        validation.validate_exp = false;
+        let data =
+            decode_jws_compact::<String>(&token, &validation, b"old", Algorithm::HS256).unwrap();
+        assert_eq!(data.claims.subject(), "john");
+
+        validation.validate_nbf = true;
+        let err = decode_jws_compact::<String>(&token, &validation, b"old", Algorithm::HS256)
+            .unwrap_err();
+        assert_eq!(err.kind(), crate::ErrorKind::MissingClaim);
+    }
+
+    #[test]
+    fn applies_leeway() {
+        let mut authn = config_map();
+
+        let account = AccountId::new("john", "example.org");
+        let token = TokenBuilder::new()
+            .issuer("iam.example.org")
+            .subject(&account)
+            .expires_in(-10)
+            .key(Algorithm::HS256, b"new")
+            .build()
+            .unwrap();
+        assert!(decode_jws_compact_with_config::<String>(&token, &authn).is_err());
+
+        let config = authn.remove("iam.example.org").unwrap().with_leeway(30);
+        authn.insert("iam.example.org".to_owned(), config);
+        assert!(decode_jws_compact_with_config::<String>(&token, &authn).is_ok());
+    }
+
+    #[test]
+    fn verifies_with_jwks_key() {
+        let jwks = serde_json::from_value(serde_json::json!({
+            "keys": [{
+                "kty": "EC",
+                "kid": "foo",
+                "crv": "P-256",
+                "x": "2a6We1soiXiB0F4NmLW5Yt_PaECY26RO0lsojO66VWs",
+                "y": "AMmFtxcK-jFgXsZ2O7SuxGgvB2QbxoCp1S7P0BxqRMA",
+            }]
+        }))
+        .unwrap();
+
+        let audience = vec!["example.org".to_owned()].into_iter().collect();
+        let mut authn = ConfigMap::new();
+        authn.insert(
+            "iam.example.org".to_owned(),
+            Config::from_jwks(audience, &jwks).unwrap(),
+        );
+
+        let key = std::fs::read("data/keys/foo.private.der.example").unwrap();
+        let account = AccountId::new("john", "example.org");
+        let token = TokenBuilder::new()
+            .issuer("iam.example.org")
+            .subject(&account)
+            .key(Algorithm::ES256, &key)
+            .key_id("foo")
+            .build()
+            .unwrap();
+
+        assert!(decode_jws_compact_with_config::<String>(&token, &authn).is_ok());
+    }
+
+    #[test]
+    fn verifies_rsa_keys() {
+        let account = AccountId::new("john", "example.org");
+
+        let cases = [
+            (
+                "data/keys/bar.private.pem.example",
+                "data/keys/bar.public.der.example",
+            ),
+            (
+                "data/keys/bar.private.der.example",
+                "data/keys/bar.public.pem.example",
+            ),
+        ];
+
+        for (private_key, public_key) in cases.iter() {
+            let private_key = std::fs::read(private_key).unwrap();
+            let public_key = std::fs::read(public_key).unwrap();
+
+            for algorithm in [Algorithm::RS256, Algorithm::RS512, Algorithm::PS384].iter() {
+                let authn = config_map_with(vec![Key::new(None, *algorithm, &public_key)]);
+
+                let token = TokenBuilder::new()
+                    .issuer("iam.example.org")
+                    .subject(&account)
+                    .key(*algorithm, &private_key)
+                    .build()
+                    .unwrap();
+
+                assert!(decode_jws_compact_with_config::<String>(&token, &authn).is_ok());
+            }
+        }
+    }
+
+    #[test]
+    fn verifies_ed25519_keys() {
+        let private_key = std::fs::read("data/keys/baz.private.der.example").unwrap();
+        let public_key = std::fs::read("data/keys/baz.public.der.example").unwrap();
+
+        let authn = config_map_with(vec![Key::new(None, Algorithm::EdDSA, &public_key)]);
+
+        let account = AccountId::new("john", "example.org");
+        let token = TokenBuilder::new()
+            .issuer("iam.example.org")
+            .subject(&account)
+            .key(Algorithm::EdDSA, &private_key)
+            .build()
+            .unwrap();
+
+        assert!(decode_jws_compact_with_config::<String>(&token, &authn).is_ok());
+    }
+
+    #[test]
+    fn verifies_pem_encoded_keys() {
+        let account = AccountId::new("john", "example.org");
+
+        // PKCS#8, PKCS#1 and SEC1 private keys, SPKI and PKCS#1 public keys.
+        let cases = [
+            (
+                Algorithm::RS256,
+                "data/keys/bar.private.p8.pem.example",
+                "data/keys/bar.public.spki.pem.example",
+            ),
+            (
+                Algorithm::PS256,
+                "data/keys/bar.private.pem.example",
+                "data/keys/bar.public.pem.example",
+            ),
+            (
+                Algorithm::ES256,
+                "data/keys/foo.private.pem.example",
+                "data/keys/foo.public.spki.pem.example",
+            ),
+            (
+                Algorithm::EdDSA,
+                "data/keys/baz.private.p8.pem.example",
+                "data/keys/baz.public.spki.pem.example",
+            ),
+        ];
+
+        for (algorithm, private_key, public_key) in cases.iter() {
+            let private_key = std::fs::read(private_key).unwrap();
+            let public_key = std::fs::read(public_key).unwrap();
+
+            let authn = config_map_with(vec![Key::new(None, *algorithm, &public_key)]);
+
+            let token = TokenBuilder::new()
+                .issuer("iam.example.org")
+                .subject(&account)
+                .key(*algorithm, &private_key)
+                .build()
+                .unwrap();
+
+            let data = decode_jws_compact_with_config::<String>(&token, &authn).unwrap();
+            assert_eq!(data.header.alg, *algorithm);
+        }
+    }
+
+    #[test]
+    fn verifies_hmac_and_ecdsa_variants() {
+        let account = AccountId::new("john", "example.org");
+        let es384_private_key = std::fs::read("data/keys/qux.private.der.example").unwrap();
+        let es384_public_key = std::fs::read("data/keys/qux.public.der.example").unwrap();
+
+        let cases = [
+            (Algorithm::HS384, &b"secret"[..], &b"secret"[..]),
+            (Algorithm::HS512, &b"secret"[..], &b"secret"[..]),
+            (
+                Algorithm::ES384,
+                &es384_private_key[..],
+                &es384_public_key[..],
+            ),
+        ];
+
+        for (algorithm, private_key, public_key) in cases.iter() {
+            let authn = config_map_with(vec![Key::new(None, *algorithm, public_key)]);
+
+            let token = TokenBuilder::new()
+                .issuer("iam.example.org")
+                .subject(&account)
+                .key(*algorithm, private_key)
+                .build()
+                .unwrap();
+
+            assert!(decode_jws_compact_with_config::<String>(&token, &authn).is_ok());
+        }
+    }
+
+    #[test]
+    fn verifies_with_prepared_verifier() {
+        fn assert_shareable<T: Send + Sync>(_: &T) {}
+
+        let verifier = Verifier::new(&config_map()).unwrap();
+        assert_shareable(&verifier);
+
+        let token = build_token(Some("1"), b"old");
+        let data = verifier.verify::<String>(&token).unwrap();
+        assert_eq!(data.claims.subject(), "john");
+
+        let token = build_token(None, b"unknown");
+        assert!(verifier.verify::<String>(&token).is_err());
+    }
+
+    #[test]
+    fn accepts_any_allowed_audience() {
+        let authn = config_map();
+
+        let mut claims = Claims::new("iam.example.org", "example.net", "john");
+        claims.add_audience("example.org");
+        let token = encode(
+            &Header::new(Algorithm::HS256),
+            &claims,
+            &EncodingKey::from_secret(b"new"),
+        )
+        .unwrap();
+        let data = decode_jws_compact_with_config::<String>(&token, &authn).unwrap();
+        assert_eq!(data.claims.audiences(), ["example.net", "example.org"]);
+        // The account is derived from the allowed audience rather than the first one.
+        assert_eq!(data.claims.audience(), "example.org");
+        assert_eq!(
+            AccountId::from(data.claims),
+            AccountId::new("john", "example.org")
+        );
+        let data = Verifier::new(&authn)
+            .unwrap()
+            .verify::<String>(&token)
+            .unwrap();
+        assert_eq!(data.claims.audience(), "example.org");
+        assert_eq!(
+            parse_jws_compact::<String>(&token)
+                .unwrap()
+                .claims
+                .audience(),
+            "example.net"
+        );
+
+        let claims = Claims::new("iam.example.org", "example.net", "john");
+        let token = encode(
+            &Header::new(Algorithm::HS256),
+            &claims,
+            &EncodingKey::from_secret(b"new"),
+        )
+        .unwrap();
+        assert!(decode_jws_compact_with_config::<String>(&token, &authn).is_err());
+    }
+
+    #[test]
+    fn restricts_target_audiences() {
+        let mut authn = config_map();
+
+        let account = AccountId::new("john", "example.org");
+        let builder = TokenBuilder::new()
+            .issuer("iam.example.org")
+            .subject(&account)
+            .key(Algorithm::HS256, b"new");
+
+        let token = builder
+            .clone()
+            .target_audience("example.net")
+            .build()
+            .unwrap();
+        let data = decode_jws_compact_with_config::<String>(&token, &authn).unwrap();
+        assert_eq!(
+            data.claims.cross_audience().unwrap(),
+            CrossAudience::new("example.org", Some("example.net"))
+        );
+
+        let targets = vec!["example.com".to_owned()].into_iter().collect();
+        let config = authn.remove("iam.example.org").unwrap();
+        authn.insert(
+            "iam.example.org".to_owned(),
+            config.with_allowed_targets(targets),
+        );
+        let err = decode_jws_compact_with_config::<String>(&token, &authn).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "target audience = example.net of the authentication token is not allowed"
+        );
+
+        let token = builder
+            .clone()
+            .target_audience("example.com")
+            .build()
+            .unwrap();
+        assert!(decode_jws_compact_with_config::<String>(&token, &authn).is_ok());
+
+        let token = builder.build().unwrap();
+        assert!(decode_jws_compact_with_config::<String>(&token, &authn).is_ok());
+    }
+
+    #[test]
+    fn preserves_extra_claims() {
+        let authn = config_map();
+
+        let account = AccountId::new("john", "example.org");
+        let builder = TokenBuilder::new()
+            .issuer("iam.example.org")
+            .subject(&account)
+            .key(Algorithm::HS256, b"new")
+            .claim("tenant", serde_json::json!("acme"))
+            .claim("features", serde_json::json!(["beta"]));
+
+        let token = builder.clone().build().unwrap();
+        let data = decode_jws_compact_with_config::<String>(&token, &authn).unwrap();
+        assert_eq!(data.claims.subject(), "john");
+        assert_eq!(
+            data.claims.extra_claim("tenant"),
+            Some(&serde_json::json!("acme"))
+        );
+        assert_eq!(data.claims.extra_claims().len(), 2);
+
+        assert!(builder
+            .claim("iss", serde_json::json!("evil.example.org"))
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn rejects_revoked_tokens() {
+        let list = std::sync::Arc::new(InMemoryRevocationList::new());
+        let verifier = Verifier::new(&config_map())
+            .unwrap()
+            .with_revocation_list(list.clone());
+
+        let token = build_token(None, b"new");
+        assert!(verifier.verify::<String>(&token).is_ok());
+
+        list.revoke_token_hash(&token_hash(&token));
+        assert!(verifier.verify::<String>(&token).is_err());
+
+        let token = build_token(Some("1"), b"old");
+        assert!(verifier.verify::<String>(&token).is_ok());
+
+        let now = Utc::now().timestamp() as u64;
+        list.revoke_account_before(&AccountId::new("john", "example.org"), now + 1);
+        let err = verifier.verify::<String>(&token).unwrap_err();
+        assert_eq!(err.to_string(), "the authentication token has been revoked");
+
+        // A cross-audience token is revoked along with other tokens of the account.
+        let token = TokenBuilder::new()
+            .issuer("iam.example.org")
+            .subject(&AccountId::new("john", "example.org"))
+            .target_audience("example.net")
+            .key(Algorithm::HS256, b"new")
+            .build()
+            .unwrap();
+        let err = verifier.verify::<String>(&token).unwrap_err();
+        assert_eq!(err.kind(), crate::ErrorKind::Revoked);
+
+        // So is a token whose first audience isn't allowed.
+        let mut claims = Claims::new("iam.example.org", "evil.net", "john");
+        claims.add_audience("example.org");
+        let token = encode(
+            &Header::new(Algorithm::HS256),
+            &claims,
+            &EncodingKey::from_secret(b"new"),
+        )
+        .unwrap();
+        let err = verifier.verify::<String>(&token).unwrap_err();
+        assert_eq!(err.kind(), crate::ErrorKind::Revoked);
+    }
+
+    #[test]
+    fn rejects_replayed_tokens() {
+        let cache = InMemoryReplayCache::new(std::time::Duration::from_secs(60));
+        let verifier = Verifier::new(&config_map())
+            .unwrap()
+            .with_replay_cache(std::sync::Arc::new(cache));
+
+        let account = AccountId::new("john", "example.org");
+        let builder = TokenBuilder::new()
+            .issuer("iam.example.org")
+            .subject(&account)
+            .expires_in(60)
+            .key(Algorithm::HS256, b"new");
+
+        let token = builder.clone().generate_token_id().build().unwrap();
+        let data = verifier.verify::<String>(&token).unwrap();
+        assert_eq!(data.claims.token_id().map(str::len), Some(22));
+        assert!(verifier.verify::<String>(&token).is_err());
+
+        let token = builder.clone().generate_token_id().build().unwrap();
+        assert!(verifier.verify::<String>(&token).is_ok());
+
+        let token = builder.clone().token_id("1").build().unwrap();
+        assert!(verifier.verify::<String>(&token).is_ok());
+        assert!(verifier.verify::<String>(&token).is_err());
+
+        let token = builder.build().unwrap();
+        assert!(verifier.verify::<String>(&token).is_err());
+    }
+
+    #[test]
+    fn extracts_tokens_from_request() {
+        use http::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
+        use http::Uri;
+
+        use super::extract::{
+            extract_jws_compact_from_form, extract_jws_compact_from_query,
+            extract_jws_compact_from_request,
+        };
+
+        let token = build_token(Some("1"), b"old");
+        let uri = format!("/a?access_token={}", token).parse::<Uri>().unwrap();
+        let data = extract_jws_compact_from_query::<String>(&uri, &config_map()).unwrap();
+        assert_eq!(data.claims.subject(), "john");
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("application/x-www-form-urlencoded"),
+        );
+        let body = format!("access_token={}", token);
+        let data =
+            extract_jws_compact_from_form::<String>(&headers, body.as_bytes(), &config_map())
+                .unwrap();
+        assert_eq!(data.claims.subject(), "john");
+
+        let err = extract_jws_compact_from_query::<String>(&Uri::from_static("/a"), &config_map())
+            .unwrap_err();
+        assert_eq!(err.kind(), crate::ErrorKind::MissingToken);
+
+        let err = extract_jws_compact_from_request::<String>(
+            &headers,
+            &uri,
+            Some(body.as_bytes()),
+            &config_map(),
+        )
+        .unwrap_err();
+        assert_eq!(err.kind(), crate::ErrorKind::MalformedRequest);
+    }
+
+    #[test]
+    fn extracts_tokens_from_cookie() {
+        use http::header::{HeaderMap, HeaderValue, COOKIE};
+
+        use super::extract::extract_jws_compact_from_cookie;
+
+        let token = build_token(Some("1"), b"old");
+        let mut headers = HeaderMap::new();
+        headers.append(COOKIE, HeaderValue::from_static("theme=dark"));
+        headers.append(
+            COOKIE,
+            HeaderValue::from_str(&format!("lang=en; access_token={}", token)).unwrap(),
+        );
+
+        let data =
+            extract_jws_compact_from_cookie::<String>(&headers, "access_token", &config_map())
+                .unwrap();
+        assert_eq!(data.claims.subject(), "john");
+
+        let err = extract_jws_compact_from_cookie::<String>(&headers, "token", &config_map())
+            .unwrap_err();
+        assert_eq!(err.kind(), crate::ErrorKind::MissingToken);
+    }
+
+    #[test]
+    fn extracts_tokens_with_verifier() {
+        use http::header::{HeaderMap, HeaderValue, COOKIE};
+        use http::Uri;
+
+        use super::extract::{
+            extract_jws_compact_from_cookie_with_verifier,
+            extract_jws_compact_from_query_with_verifier,
+        };
+
+        let cache = InMemoryReplayCache::new(std::time::Duration::from_secs(60));
+        let verifier = Verifier::new(&config_map())
+            .unwrap()
+            .with_replay_cache(std::sync::Arc::new(cache));
+
+        let token = TokenBuilder::new()
+            .issuer("iam.example.org")
+            .subject(&AccountId::new("john", "example.org"))
+            .generate_token_id()
+            .key(Algorithm::HS256, b"new")
+            .build()
+            .unwrap();
+
+        let uri = format!("/a?access_token={}", token).parse::<Uri>().unwrap();
+        let data = extract_jws_compact_from_query_with_verifier::<String>(&uri, &verifier).unwrap();
+        assert_eq!(data.claims.subject(), "john");
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            COOKIE,
+            HeaderValue::from_str(&format!("access_token={}", token)).unwrap(),
+        );
+        let err = extract_jws_compact_from_cookie_with_verifier::<String>(
+            &headers,
+            "access_token",
+            &verifier,
+        )
+        .unwrap_err();
+        assert_eq!(err.kind(), crate::ErrorKind::Replayed);
     }
 }
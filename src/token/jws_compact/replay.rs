@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::Utc;
+
+use crate::{Error, ErrorKind};
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// A storage of identifiers (`jti`) of already used tokens.
+pub trait ReplayCache: Send + Sync {
+    /// Records the identifier of a token of the issuer until the specified time
+    /// as a Unix timestamp or, if it's absent, for a storage specific period.
+    ///
+    /// Returns `false` if the identifier has already been recorded and isn't expired yet.
+    fn insert(&self, issuer: &str, token_id: &str, expires_at: Option<u64>) -> Result<bool, Error>;
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+const MIN_PURGE_THRESHOLD: usize = 1024;
+
+/// An in-memory replay cache of a single process.
+///
+/// Expired identifiers are purged once the number of records doubles.
+#[derive(Debug)]
+pub struct InMemoryReplayCache {
+    ttl: Duration,
+    inner: Mutex<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    records: HashMap<(String, String), u64>,
+    purge_threshold: usize,
+}
+
+impl InMemoryReplayCache {
+    /// Creates a cache keeping identifiers of tokens without `exp` for the specified period.
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            inner: Mutex::new(Inner {
+                records: HashMap::new(),
+                purge_threshold: MIN_PURGE_THRESHOLD,
+            }),
+        }
+    }
+}
+
+impl ReplayCache for InMemoryReplayCache {
+    fn insert(&self, issuer: &str, token_id: &str, expires_at: Option<u64>) -> Result<bool, Error> {
+        let now = Utc::now().timestamp() as u64;
+        let expires_at = expires_at.unwrap_or_else(|| now + self.ttl.as_secs());
+
+        let mut inner = self
+            .inner
+            .lock()
+            .map_err(|_| Error::with_kind(ErrorKind::Internal, "replay cache lock is poisoned"))?;
+
+        if inner.records.len() >= inner.purge_threshold {
+            inner.records.retain(|_, value| *value >= now);
+            inner.purge_threshold = std::cmp::max(inner.records.len() * 2, MIN_PURGE_THRESHOLD);
+        }
+
+        let key = (issuer.to_owned(), token_id.to_owned());
+        match inner.records.get(&key) {
+            Some(value) if *value >= now => Ok(false),
+            _ => {
+                inner.records.insert(key, expires_at);
+                Ok(true)
+            }
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_recorded_token_ids() {
+        let cache = InMemoryReplayCache::new(Duration::from_secs(60));
+        let now = Utc::now().timestamp() as u64;
+
+        assert!(cache
+            .insert("iam.example.org", "1", Some(now + 60))
+            .unwrap());
+        assert!(!cache
+            .insert("iam.example.org", "1", Some(now + 60))
+            .unwrap());
+        assert!(cache
+            .insert("iam.example.net", "1", Some(now + 60))
+            .unwrap());
+        assert!(cache.insert("iam.example.org", "2", None).unwrap());
+        assert!(!cache.insert("iam.example.org", "2", None).unwrap());
+
+        // Identifiers of expired tokens may be reused.
+        assert!(cache.insert("iam.example.org", "3", Some(now - 1)).unwrap());
+        assert!(cache
+            .insert("iam.example.org", "3", Some(now + 60))
+            .unwrap());
+    }
+
+    #[test]
+    fn reports_poisoned_lock() {
+        let cache = std::sync::Arc::new(InMemoryReplayCache::new(Duration::from_secs(60)));
+
+        let poisoner = cache.clone();
+        let _ = std::thread::spawn(move || {
+            let _guard = poisoner.inner.lock().unwrap();
+            panic!("poisoning the lock");
+        })
+        .join();
+
+        let err = cache.insert("iam.example.org", "1", None).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Internal);
+    }
+}
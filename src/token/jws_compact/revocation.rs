@@ -0,0 +1,336 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError, RwLock, RwLockWriteGuard};
+use std::time::{Duration, Instant, SystemTime};
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use serde_derive::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::{AccountId, Error, ErrorKind};
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Details of a token with a verified signature to check whether it's revoked.
+#[derive(Debug)]
+pub struct RevocationCheck<'a> {
+    token: &'a str,
+    issuer: &'a str,
+    token_id: Option<&'a str>,
+    account: Option<AccountId>,
+    issued_at: Option<u64>,
+}
+
+impl<'a> RevocationCheck<'a> {
+    pub(crate) fn new(
+        token: &'a str,
+        issuer: &'a str,
+        token_id: Option<&'a str>,
+        account: Option<AccountId>,
+        issued_at: Option<u64>,
+    ) -> Self {
+        Self {
+            token,
+            issuer,
+            token_id,
+            account,
+            issued_at,
+        }
+    }
+
+    pub fn issuer(&self) -> &str {
+        self.issuer
+    }
+
+    pub fn token_id(&self) -> Option<&str> {
+        self.token_id
+    }
+
+    /// A base64url encoded SHA-256 hash of the token.
+    pub fn token_hash(&self) -> String {
+        token_hash(self.token)
+    }
+
+    /// An account of the token when its subject is a string.
+    pub fn account(&self) -> Option<&AccountId> {
+        self.account.as_ref()
+    }
+
+    pub fn issued_at(&self) -> Option<u64> {
+        self.issued_at
+    }
+}
+
+/// Returns a base64url encoded SHA-256 hash of the token used to revoke it.
+pub fn token_hash(token: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(token.as_bytes()))
+}
+
+/// A storage of revoked tokens consulted once the signature of a token is verified.
+pub trait RevocationList: Send + Sync {
+    fn is_revoked(&self, token: &RevocationCheck) -> Result<bool, Error>;
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// An in-memory list of revoked tokens.
+///
+/// Tokens may be revoked by an issuer and `jti`, by a hash of the whole token
+/// or by an account, in which case tokens issued before the specified time
+/// (and ones without `iat`) are revoked.
+#[derive(Debug, Default)]
+pub struct InMemoryRevocationList {
+    inner: RwLock<Revocations>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Revocations {
+    token_ids: HashSet<TokenId>,
+    token_hashes: HashSet<String>,
+    accounts: HashMap<AccountId, u64>,
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Deserialize)]
+struct TokenId {
+    iss: String,
+    jti: String,
+}
+
+impl InMemoryRevocationList {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn revoke_token_id(&self, issuer: &str, token_id: &str) {
+        self.write().token_ids.insert(TokenId {
+            iss: issuer.to_owned(),
+            jti: token_id.to_owned(),
+        });
+    }
+
+    /// Revokes a token by its hash, see [`token_hash`].
+    pub fn revoke_token_hash(&self, value: &str) {
+        self.write().token_hashes.insert(value.to_owned());
+    }
+
+    /// Revokes tokens of the account issued before the time as a Unix timestamp.
+    pub fn revoke_account_before(&self, account: &AccountId, timestamp: u64) {
+        let mut inner = self.write();
+        let value = inner.accounts.entry(account.to_owned()).or_insert(0);
+        *value = std::cmp::max(*value, timestamp);
+    }
+
+    fn replace(&self, value: Revocations) {
+        *self.write() = value;
+    }
+
+    // A revocation is never lost because of a poisoned lock,
+    // checks still fail with an internal error then.
+    fn write(&self) -> RwLockWriteGuard<'_, Revocations> {
+        self.inner.write().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl RevocationList for InMemoryRevocationList {
+    fn is_revoked(&self, token: &RevocationCheck) -> Result<bool, Error> {
+        let inner = self.inner.read().map_err(|_| {
+            Error::with_kind(ErrorKind::Internal, "revocation list lock is poisoned")
+        })?;
+
+        if let Some(token_id) = token.token_id() {
+            let key = TokenId {
+                iss: token.issuer().to_owned(),
+                jti: token_id.to_owned(),
+            };
+            if inner.token_ids.contains(&key) {
+                return Ok(true);
+            }
+        }
+
+        if let Some(revoked_before) = token.account().and_then(|a| inner.accounts.get(a)) {
+            if !matches!(token.issued_at(), Some(iat) if iat >= *revoked_before) {
+                return Ok(true);
+            }
+        }
+
+        Ok(!inner.token_hashes.is_empty() && inner.token_hashes.contains(&token.token_hash()))
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// A list of revoked tokens read from a JSON file, e.g.:
+///
+/// ```json
+/// {
+///     "token_ids": [{ "iss": "iam.example.org", "jti": "b7f0dc5e" }],
+///     "token_hashes": ["47DEQpj8HBSa-_TImW-5JCeuQeRkm5NMpJWZG3hSuFU"],
+///     "accounts": { "john.example.org": 1700000000 }
+/// }
+/// ```
+///
+/// The file is checked for modifications at most once per the specified interval.
+/// A file that fails to be read or parsed never replaces the previous list.
+#[derive(Debug)]
+pub struct FileRevocationList {
+    path: PathBuf,
+    interval: Duration,
+    list: InMemoryRevocationList,
+    state: Mutex<(Instant, Option<SystemTime>)>,
+}
+
+impl FileRevocationList {
+    pub fn new<P>(path: P, interval: Duration) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let value = Self {
+            path: path.as_ref().to_owned(),
+            interval,
+            list: InMemoryRevocationList::new(),
+            state: Mutex::new((Instant::now(), None)),
+        };
+
+        value.reload()?;
+        Ok(value)
+    }
+
+    /// Reads the file and replaces the list if it's valid.
+    pub fn reload(&self) -> Result<(), Error> {
+        let modified = std::fs::metadata(&self.path)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        let data = std::fs::read(&self.path).map_err(|err| {
+            Error::with_kind(
+                ErrorKind::InvalidConfig,
+                &format!(
+                    "failed to read the revocation list from {} – {}",
+                    self.path.display(),
+                    err
+                ),
+            )
+            .with_source(err)
+        })?;
+        let revocations = serde_json::from_slice::<Revocations>(&data).map_err(|err| {
+            Error::with_kind(
+                ErrorKind::InvalidConfig,
+                &format!("invalid revocation list – {}", err),
+            )
+            .with_source(err)
+        })?;
+
+        self.list.replace(revocations);
+        if let Ok(mut state) = self.state.lock() {
+            *state = (Instant::now(), modified);
+        }
+        Ok(())
+    }
+
+    fn reload_if_modified(&self) {
+        let mut state = match self.state.lock() {
+            Ok(state) => state,
+            Err(_) => return,
+        };
+        if state.0.elapsed() < self.interval {
+            return;
+        }
+
+        state.0 = Instant::now();
+        let modified = std::fs::metadata(&self.path)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        if modified != state.1 {
+            state.1 = modified;
+            drop(state);
+            // NOTE: the previous list remains active when the file is invalid.
+            let _ = self.reload();
+        }
+    }
+}
+
+impl RevocationList for FileRevocationList {
+    fn is_revoked(&self, token: &RevocationCheck) -> Result<bool, Error> {
+        self.reload_if_modified();
+        self.list.is_revoked(token)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check<'a>(token: &'a str, token_id: &'a str, iat: u64) -> RevocationCheck<'a> {
+        let account = AccountId::new("john", "example.org");
+        RevocationCheck::new(
+            token,
+            "iam.example.org",
+            Some(token_id),
+            Some(account),
+            Some(iat),
+        )
+    }
+
+    #[test]
+    fn revokes_tokens() {
+        let list = InMemoryRevocationList::new();
+        assert!(!list.is_revoked(&check("a.b.c", "1", 100)).unwrap());
+
+        list.revoke_token_id("iam.example.org", "1");
+        assert!(list.is_revoked(&check("a.b.c", "1", 100)).unwrap());
+        assert!(!list.is_revoked(&check("a.b.c", "2", 100)).unwrap());
+
+        list.revoke_token_hash(&token_hash("a.b.c"));
+        assert!(list.is_revoked(&check("a.b.c", "2", 100)).unwrap());
+        assert!(!list.is_revoked(&check("d.e.f", "2", 100)).unwrap());
+
+        list.revoke_account_before(&AccountId::new("john", "example.org"), 200);
+        assert!(list.is_revoked(&check("d.e.f", "2", 100)).unwrap());
+        assert!(!list.is_revoked(&check("d.e.f", "2", 200)).unwrap());
+    }
+
+    #[test]
+    fn revokes_tokens_despite_poisoned_lock() {
+        let list = std::sync::Arc::new(InMemoryRevocationList::new());
+
+        let poisoner = list.clone();
+        let _ = std::thread::spawn(move || {
+            let _guard = poisoner.inner.write().unwrap();
+            panic!("poisoning the lock");
+        })
+        .join();
+
+        list.revoke_token_id("iam.example.org", "1");
+        list.revoke_token_hash(&token_hash("a.b.c"));
+        list.revoke_account_before(&AccountId::new("john", "example.org"), 200);
+        list.replace(Revocations::default());
+
+        let err = list.is_revoked(&check("a.b.c", "1", 100)).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Internal);
+    }
+
+    #[test]
+    fn reads_revocations_from_file() {
+        let path =
+            std::env::temp_dir().join(format!("svc-authn-revocations-{}.json", std::process::id()));
+        let data = serde_json::json!({
+            "token_ids": [{ "iss": "iam.example.org", "jti": "1" }],
+            "accounts": { "john.example.org": 200 },
+        });
+        std::fs::write(&path, data.to_string()).unwrap();
+
+        let list = FileRevocationList::new(&path, Duration::from_secs(0)).unwrap();
+        assert!(list.is_revoked(&check("a.b.c", "1", 300)).unwrap());
+        assert!(list.is_revoked(&check("a.b.c", "2", 100)).unwrap());
+        assert!(!list.is_revoked(&check("a.b.c", "2", 300)).unwrap());
+
+        std::fs::write(&path, "{").unwrap();
+        assert!(list.reload().is_err());
+        assert!(list.is_revoked(&check("a.b.c", "1", 300)).unwrap());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
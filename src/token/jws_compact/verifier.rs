@@ -0,0 +1,326 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use jsonwebtoken::errors::ErrorKind as JwtErrorKind;
+use jsonwebtoken::{decode, Algorithm, DecodingKey, Header, TokenData, Validation};
+
+use super::extract::{decoding_key, parse_jws_compact, verification_error};
+use super::replay::ReplayCache;
+use super::revocation::{RevocationCheck, RevocationList};
+use crate::jose::{select_keys, AudienceSet, Claims, Config, ConfigMap, Key, Policy};
+use crate::{AccountId, CrossAudience, Error, ErrorKind};
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// A verifier of tokens with keys and validation settings prepared in advance.
+///
+/// It's built once from the app config and may be shared across threads.
+#[derive(Clone)]
+pub struct Verifier {
+    issuers: HashMap<String, IssuerVerifier>,
+    replay_cache: Option<Arc<dyn ReplayCache>>,
+    revocation_list: Option<Arc<dyn RevocationList>>,
+}
+
+impl Verifier {
+    pub fn new(authn: &ConfigMap) -> Result<Self, Error> {
+        let issuers = authn
+            .iter()
+            .map(|(issuer, config)| {
+                IssuerVerifier::new(config)
+                    .map(|verifier| (issuer.to_owned(), verifier))
+                    .map_err(|err| {
+                        Error::with_kind(
+                            ErrorKind::InvalidConfig,
+                            &format!("invalid config for issuer = {} – {}", issuer, err),
+                        )
+                        .with_source(err)
+                    })
+            })
+            .collect::<Result<HashMap<_, _>, _>>()?;
+
+        Ok(Self {
+            issuers,
+            replay_cache: None,
+            revocation_list: None,
+        })
+    }
+
+    /// Makes tokens one-time: a token is rejected if its `jti` has already been seen.
+    ///
+    /// Tokens without `jti` are rejected as well.
+    pub fn with_replay_cache(self, value: Arc<dyn ReplayCache>) -> Self {
+        Self {
+            replay_cache: Some(value),
+            ..self
+        }
+    }
+
+    /// Rejects revoked tokens. The list is consulted once the signature is verified.
+    pub fn with_revocation_list(self, value: Arc<dyn RevocationList>) -> Self {
+        Self {
+            revocation_list: Some(value),
+            ..self
+        }
+    }
+
+    pub fn verify<T>(&self, token: &str) -> Result<TokenData<Claims<T>>, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let parts = parse_jws_compact::<serde_json::Value>(token)?;
+        let verifier = self.issuers.get(parts.claims.issuer()).ok_or_else(|| {
+            Error::with_kind(
+                ErrorKind::UnknownIssuer,
+                &format!(
+                    "issuer = {} of the authentication token is not allowed",
+                    parts.claims.issuer(),
+                ),
+            )
+        })?;
+
+        let data = verifier.verify(token, &parts)?;
+
+        if let Some(ref revocation_list) = self.revocation_list {
+            // NOTE: the account is derived from the verified audience, a target audience
+            // of a cross-audience token doesn't belong to the account.
+            let account = match (
+                parts.claims.subject().as_str(),
+                data.claims.cross_audience(),
+            ) {
+                (Some(label), Ok(audience)) => Some(AccountId::new(label, audience.audience())),
+                _ => None,
+            };
+            let check = RevocationCheck::new(
+                token,
+                data.claims.issuer(),
+                data.claims.token_id(),
+                account,
+                data.claims.issued_at(),
+            );
+            if revocation_list.is_revoked(&check)? {
+                return Err(Error::with_kind(
+                    ErrorKind::Revoked,
+                    "the authentication token has been revoked",
+                ));
+            }
+        }
+
+        if let Some(ref replay_cache) = self.replay_cache {
+            let token_id = data.claims.token_id().ok_or_else(|| {
+                Error::with_kind(
+                    ErrorKind::MissingClaim,
+                    "missing 'jti' claim of the authentication token",
+                )
+            })?;
+            // NOTE: the token is accepted until 'exp' plus leeway, so it must be kept as long.
+            let expires_at = data
+                .claims
+                .expiration_time()
+                .map(|exp| exp + verifier.leeway);
+            if !replay_cache.insert(data.claims.issuer(), token_id, expires_at)? {
+                return Err(Error::with_kind(
+                    ErrorKind::Replayed,
+                    &format!(
+                        "token id = {} of the authentication token has already been used",
+                        token_id
+                    ),
+                ));
+            }
+        }
+
+        Ok(data)
+    }
+}
+
+impl fmt::Debug for Verifier {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Verifier")
+            .field("issuers", &self.issuers.keys())
+            .field("replay_cache", &self.replay_cache.is_some())
+            .field("revocation_list", &self.revocation_list.is_some())
+            .finish()
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Clone)]
+pub(crate) struct IssuerVerifier {
+    audience: AudienceSet,
+    allowed_targets: Option<HashSet<String>>,
+    keys: Vec<PreparedKey>,
+    leeway: u64,
+    policy: Policy,
+}
+
+#[derive(Clone)]
+struct PreparedKey {
+    kid: Option<String>,
+    algorithm: Algorithm,
+    decoding_key: Option<DecodingKey>,
+    validation: Validation,
+}
+
+impl IssuerVerifier {
+    pub(crate) fn new(config: &Config) -> Result<Self, Error> {
+        Self::prepare(config, config.keys().iter().collect(), None)
+    }
+
+    /// Prepares only the keys the token may be verified with, so that other keys
+    /// of the issuer are neither parsed on every call nor able to fail the verification.
+    pub(crate) fn for_token(config: &Config, header: &Header) -> Result<Self, Error> {
+        Self::prepare(
+            config,
+            config.keys_for(header.kid.as_deref()),
+            Some(header.alg),
+        )
+    }
+
+    fn prepare(
+        config: &Config,
+        keys: Vec<&Key>,
+        algorithm: Option<Algorithm>,
+    ) -> Result<Self, Error> {
+        let keys = keys
+            .into_iter()
+            .map(|key| {
+                // NOTE: 'exp' and 'nbf' are only validated when they're present in the token.
+                let mut validation = Validation::new(key.algorithm());
+                validation.leeway = config.leeway();
+                validation.validate_nbf = true;
+                validation.required_spec_claims = HashSet::new();
+
+                // NOTE: keys of another algorithm can't verify the token, they're kept unparsed
+                // only to report the unsupported algorithm rather than the unknown key.
+                let decoding_key = match algorithm {
+                    Some(algorithm) if algorithm != key.algorithm() => None,
+                    _ => Some(decoding_key(key.key(), key.algorithm())?),
+                };
+
+                Ok(PreparedKey {
+                    kid: key.kid().map(ToOwned::to_owned),
+                    algorithm: key.algorithm(),
+                    decoding_key,
+                    validation,
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        Ok(Self {
+            audience: AudienceSet::new(config.audience())?,
+            allowed_targets: config.allowed_targets().cloned(),
+            keys,
+            leeway: config.leeway(),
+            policy: config.policy().clone(),
+        })
+    }
+
+    pub(crate) fn verify<T, U>(
+        &self,
+        token: &str,
+        parts: &TokenData<Claims<U>>,
+    ) -> Result<TokenData<Claims<T>>, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        // NOTE: we consider the token valid if any of its audiences matches at least
+        // one audience from the app config for the same issuer.
+        // We can't use 'verifier.set_audience(&config.audience)' because it's
+        // succeed if only all values from the config represented in the token.
+        //
+        // If audience is a cross-audience '{audience}:{target}' we check the first part
+        // and then the target if targets are restricted for the issuer.
+        //
+        // The first of matched audiences becomes the audience of verified claims,
+        // so that the account is never derived from an audience that hasn't been checked.
+        let audiences = parts
+            .claims
+            .audiences()
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, audience)| {
+                CrossAudience::from_str(audience)
+                    .ok()
+                    .map(|audience| (idx, audience))
+            })
+            .filter(|(_, audience)| self.audience.contains(audience.audience()))
+            .collect::<Vec<_>>();
+        if audiences.is_empty() {
+            return Err(Error::with_kind(
+                ErrorKind::DisallowedAudience,
+                &format!(
+                    "audience = {} of the authentication token is not allowed",
+                    parts.claims.audiences().join(", "),
+                ),
+            ));
+        }
+
+        let verified_audience = match self.allowed_targets {
+            Some(ref targets) => audiences
+                .iter()
+                .find(|(_, audience)| match audience.target() {
+                    Some(target) => targets.contains(target),
+                    None => true,
+                })
+                .map(|(idx, _)| *idx)
+                .ok_or_else(|| {
+                    Error::with_kind(
+                        ErrorKind::DisallowedTarget,
+                        &format!(
+                            "target audience = {} of the authentication token is not allowed",
+                            audiences
+                                .iter()
+                                .filter_map(|(_, audience)| audience.target())
+                                .collect::<Vec<_>>()
+                                .join(", "),
+                        ),
+                    )
+                })?,
+            None => audiences[0].0,
+        };
+
+        let kid = parts.header.kid.as_deref();
+        let keys = select_keys(&self.keys, kid, |key| key.kid.as_deref());
+        if keys.is_empty() {
+            return Err(Error::with_kind(
+                ErrorKind::UnknownKey,
+                &format!(
+                    "key id = {} of the authentication token is not allowed",
+                    kid.unwrap_or_default(),
+                ),
+            ));
+        }
+
+        // NOTE: we try keys one by one until the signature matches,
+        // so that tokens signed with the previous key remain valid while it's being rotated.
+        let mut result = Err(Error::with_kind(
+            ErrorKind::UnsupportedAlgorithm,
+            &format!(
+                "algorithm = {:?} of the authentication token is not allowed",
+                parts.header.alg,
+            ),
+        ));
+        for (key, decoding_key) in keys
+            .into_iter()
+            .filter(|key| key.algorithm == parts.header.alg)
+            .filter_map(|key| key.decoding_key.as_ref().map(|value| (key, value)))
+        {
+            match decode(token, decoding_key, &key.validation) {
+                Err(err) if matches!(err.kind(), JwtErrorKind::InvalidSignature) => {
+                    result = Err(verification_error(err));
+                }
+                value => {
+                    let mut data: TokenData<Claims<T>> = value.map_err(verification_error)?;
+                    data.claims.set_verified_audience(verified_audience);
+                    self.policy.check(&data.claims)?;
+                    return Ok(data);
+                }
+            }
+        }
+
+        result
+    }
+}